@@ -0,0 +1,293 @@
+use bracket_lib::prelude::{to_char, RandomNumberGenerator};
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process;
+use tcc_ufsm_2020::{
+    map_gen::{CustomRegion, Map, MapGenerator, Symmetry},
+    raws, rexloader,
+};
+
+/*
+ *
+ * mapgen.rs
+ * ---------
+ * Runs a single map generator and exports the result as text and PNG, without opening a window.
+ * Same seed and parameters always give the same map, so outputs can be compared in batch.
+ *
+ * Usage: mapgen <routine> [options]
+ *
+ */
+
+const USAGE: &str = "Usage: mapgen <routine> [options]
+
+Routines:
+    bsp, bsp_ruin, cave, tight_cave, forest, digger, digger_inverted, wfc
+    (the gen_ prefix of the MapGenerator functions is also accepted)
+    level: a whole level, from the recipes in resources/raws/levels.ron (depth = --level + 1)
+
+Options:
+    --seed <hex>         Run seed (random if not given).
+    --level <n>          Level whose sub-seed is used (default: 0).
+    --size <w>x<h>       Map size (default: 80x60).
+    --rect <x,y,w,h>     Generate only inside this rectangular region (x,y: top-left corner).
+    --circle <x,y,r>     Generate only inside this circular region (x,y: centre).
+    --template <name>    WFC input, as in resources/rex (default: wfc_20x20).
+    --pattern <w>x<h>    WFC input size (default: 20x20).
+    --tile-size <n>      WFC tile size (default: 10).
+    --mix-match          WFC: allow tiles to match in any direction.
+    --symmetry <s>       WFC: orientations of the input used: none, mirror (default) or all
+                         (mirrors and rotations).
+    --scale <n>          Pixels per map cell in the PNG (default: 8).
+    --out <path>         Output path, without extension (default: mapgen_<routine>_<seed>).
+";
+
+struct Options {
+    routine: String,
+    seed: u64,
+    level: usize,
+    width: i32,
+    height: i32,
+    region: Option<CustomRegion>,
+    template: String,
+    pattern: (i32, i32),
+    tile_size: i32,
+    mix_match: bool,
+    symmetry: Symmetry,
+    scale: u32,
+    out: Option<String>,
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    process::exit(1);
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str, opt: &str) -> T {
+    s.trim()
+        .parse::<T>()
+        .unwrap_or_else(|_| fail(&format!("Invalid value for {}: {}", opt, s)))
+}
+
+/// Parses "<a><sep><b><sep>...", e.g. "80x60" or "0,0,40,30".
+fn parse_list(s: &str, sep: char, len: usize, opt: &str) -> Vec<i32> {
+    let values: Vec<i32> = s.split(sep).map(|v| parse_num(v, opt)).collect();
+    if values.len() != len {
+        fail(&format!("Invalid value for {}: {}", opt, s));
+    }
+    values
+}
+
+fn parse_args() -> Options {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "--help" || args[0] == "-h" {
+        println!("{}", USAGE);
+        process::exit(0);
+    }
+
+    let mut opts = Options {
+        routine: args[0].trim_start_matches("gen_").to_string(),
+        seed: RandomNumberGenerator::new().next_u64(),
+        level: 0,
+        width: 80,
+        height: 60,
+        region: None,
+        template: "wfc_20x20".to_string(),
+        pattern: (20, 20),
+        tile_size: 10,
+        mix_match: false,
+        symmetry: Symmetry::Mirror,
+        scale: 8,
+        out: None,
+    };
+
+    let mut i = 1;
+    while i < args.len() {
+        let opt = args[i].as_str();
+        if opt == "--mix-match" {
+            opts.mix_match = true;
+            i += 1;
+            continue;
+        }
+
+        let val = match args.get(i + 1) {
+            Some(val) => val.as_str(),
+            None => fail(&format!("Missing value for {}", opt)),
+        };
+        match opt {
+            "--seed" => {
+                opts.seed = u64::from_str_radix(val, 16)
+                    .unwrap_or_else(|_| fail(&format!("Invalid seed: {}", val)))
+            }
+            "--level" => opts.level = parse_num(val, opt),
+            "--size" => {
+                let v = parse_list(val, 'x', 2, opt);
+                opts.width = v[0];
+                opts.height = v[1];
+            }
+            "--rect" => {
+                let v = parse_list(val, ',', 4, opt);
+                opts.region = Some(CustomRegion::new_rect(v[0], v[1], v[2], v[3]));
+            }
+            "--circle" => {
+                let v = parse_list(val, ',', 3, opt);
+                // new_circ takes the top-left corner of the circle's bounding square.
+                opts.region = Some(CustomRegion::new_circ(v[0] - v[2], v[1] - v[2], v[2]));
+            }
+            "--template" => opts.template = val.to_string(),
+            "--pattern" => {
+                let v = parse_list(val, 'x', 2, opt);
+                opts.pattern = (v[0], v[1]);
+            }
+            "--tile-size" => opts.tile_size = parse_num(val, opt),
+            "--symmetry" => {
+                opts.symmetry = match val {
+                    "none" => Symmetry::None,
+                    "mirror" => Symmetry::Mirror,
+                    "all" => Symmetry::All,
+                    _ => fail(&format!("Invalid value for {}: {}", opt, val)),
+                }
+            }
+            "--scale" => opts.scale = parse_num(val, opt),
+            "--out" => opts.out = Some(val.to_string()),
+            _ => fail(&format!("Unknown option: {}", opt)),
+        }
+        i += 2;
+    }
+
+    if let Some(region) = &opts.region {
+        if !region.fits_in(opts.width, opts.height) {
+            fail(&format!(
+                "Region ({}, {})-({}, {}) doesn't fit in the {}x{} map",
+                region.x1, region.y1, region.x2, region.y2, opts.width, opts.height
+            ));
+        }
+    }
+
+    opts
+}
+
+/// Runs the chosen routine on the first (and only) map of the generator.
+fn generate(opts: &Options) -> Map {
+    let mut generator = MapGenerator::new(opts.seed);
+    if opts.routine == "level" {
+        // Same as in game: the level index picks both the recipe depth and the sub-seed.
+        for _ in 0..=opts.level {
+            generator.push_map(opts.width, opts.height);
+        }
+        generator.gen_map(opts.level);
+        print_wfc_stats(&generator);
+        return generator.get_map(opts.level);
+    }
+
+    generator.push_map(opts.width, opts.height);
+    generator.seed_level(opts.level);
+
+    let region = opts.region.as_ref();
+    match opts.routine.as_str() {
+        "bsp" => generator.gen_bsp(0, region),
+        "bsp_ruin" => generator.gen_bsp_ruin(0, region),
+        "cave" => generator.gen_cave(0, region),
+        "tight_cave" => generator.gen_tight_cave(0, region),
+        "forest" => generator.gen_forest(0, region),
+        "digger" => generator.gen_digger(0, region),
+        "digger_inverted" => generator.gen_digger_inverted(0, region),
+        "wfc" => generator.gen_wfc(
+            0,
+            region,
+            &format!("resources/{}.xp", opts.template),
+            opts.pattern.0,
+            opts.pattern.1,
+            opts.tile_size,
+            opts.mix_match,
+            opts.symmetry,
+        ),
+        _ => fail(&format!("Unknown routine: {}", opts.routine)),
+    }
+    generator.finish_map(0);
+    print_wfc_stats(&generator);
+
+    generator.get_map(0)
+}
+
+/// How often each WFC template ran into contradictions.
+fn print_wfc_stats(generator: &MapGenerator) {
+    for (template, stats) in generator.wfc_stats.iter() {
+        println!(
+            "{}: {} runs, {} contradictions, {} backtracks, {} retries, {} failures",
+            template,
+            stats.runs,
+            stats.contradictions,
+            stats.backtracks,
+            stats.retries,
+            stats.failures
+        );
+    }
+}
+
+/// One line per map row, with the same glyphs shown in game.
+fn write_text(map: &Map, path: &str) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    for y in 0..map.height {
+        let row: String = (0..map.width)
+            .map(|x| to_char(map.tiles[map.idx(x, y)].glyph as u8))
+            .collect();
+        writeln!(file, "{}", row)?;
+    }
+    Ok(())
+}
+
+/// Each map cell becomes a `scale`x`scale` square painted with the tile's foreground color.
+fn write_png(map: &Map, path: &str, scale: u32) -> Result<(), png::EncodingError> {
+    let (w, h) = (map.width as u32 * scale, map.height as u32 * scale);
+    let mut data = vec![0u8; (w * h * 3) as usize];
+    for py in 0..h {
+        for px in 0..w {
+            let idx = map.idx((px / scale) as i32, (py / scale) as i32);
+            let fg = map.tiles[idx].color.fg;
+            let i = ((py * w + px) * 3) as usize;
+            data[i] = (fg.r * 255.0) as u8;
+            data[i + 1] = (fg.g * 255.0) as u8;
+            data[i + 2] = (fg.b * 255.0) as u8;
+        }
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), w, h);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)
+}
+
+fn main() {
+    let opts = parse_args();
+
+    // Map tiles take their colors from the raws, and the prefab/WFC inputs are .xp resources.
+    rexloader::load_dungeons();
+    raws::load_raws();
+
+    if opts.routine == "wfc"
+        && !(opts.template.starts_with("wfc_")
+            && rexloader::is_loaded(&format!("resources/{}.xp", opts.template)))
+    {
+        fail(&format!("Unknown template: {}", opts.template));
+    }
+
+    let map = generate(&opts);
+
+    let out = opts
+        .out
+        .clone()
+        .unwrap_or_else(|| format!("mapgen_{}_{:x}", opts.routine, opts.seed));
+    let (txt, png) = (format!("{}.txt", out), format!("{}.png", out));
+    if let Err(e) = write_text(&map, &txt) {
+        fail(&format!("Couldn't write {}: {}", txt, e));
+    }
+    if let Err(e) = write_png(&map, &png, opts.scale.max(1)) {
+        fail(&format!("Couldn't write {}: {}", png, e));
+    }
+
+    println!(
+        "{} (seed {:x}, level {}): {} and {}",
+        opts.routine, opts.seed, opts.level, txt, png
+    );
+}
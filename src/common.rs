@@ -1,4 +1,5 @@
-use super::{EquipSlot, Equipable};
+use super::{EquipSlot, Equipable, Fov, Position};
+use bracket_lib::prelude::Point;
 use specs::prelude::*;
 
 /*
@@ -20,3 +21,16 @@ pub fn is_weapon(ecs: &World, equip: Entity) -> bool {
 
     false
 }
+
+/// Checks if `ent` is in the field of view of `viewer`.
+pub fn is_seen_by(
+    ent: Entity,
+    viewer: Entity,
+    positions: &ReadStorage<Position>,
+    fov: &ReadStorage<Fov>,
+) -> bool {
+    match (positions.get(ent), fov.get(viewer)) {
+        (Some(pos), Some(fov)) => fov.visible_pos.contains(&Point::new(pos.x, pos.y)),
+        _ => false,
+    }
+}
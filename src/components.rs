@@ -1,8 +1,16 @@
+use crate::map_gen::Map;
+use crate::state::{RunSeed, RunState};
+use crate::utils::dice::Dice;
 use crate::utils::directions::Direction;
-use bracket_lib::prelude::{to_cp437, ColorPair, Point, RGB};
+use bracket_lib::prelude::{to_cp437, ColorPair, Point, RandomNumberGenerator, RGB, RGBA};
+use serde::{Deserialize, Serialize};
+use specs::saveload::{ConvertSaveload, Marker};
 use specs::{prelude::*, Component};
+use specs_derive::ConvertSaveload;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::{Infallible, Infallible as NoError}; // `NoError` is required by the derive.
 use std::ops::{Add, AddAssign, Sub};
-use strum_macros::EnumString;
+use strum_macros::{Display, EnumString};
 //use std::collections::HashSet;
 
 /*
@@ -46,7 +54,7 @@ impl Sub<Direction> for Point {
     }
 }
 
-#[derive(Component, Copy, Clone, Debug)]
+#[derive(Component, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Renderable {
     pub glyph: u16,
     pub color: ColorPair,
@@ -63,10 +71,28 @@ impl Renderable {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Player {}
 
-#[derive(EnumString, Debug)]
+pub const TURN_ENERGY: i32 = 100; // Needed to act.
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Energy {
+    pub speed: i32,  // Gained each tick; the player's is 100.
+    pub energy: i32, // Acts when it reaches TURN_ENERGY.
+}
+
+impl Energy {
+    pub fn ready(&self) -> bool {
+        self.energy >= TURN_ENERGY
+    }
+}
+
+#[derive(Component, Clone)]
+// Mobs with enough energy to act in this pass of the mobs' turn.
+pub struct MyTurn {}
+
+#[derive(Display, EnumString, Debug, Clone, Serialize, Deserialize)]
 pub enum MobType {
     Gen,        // A "true" human. May be genetically/cybernetically modified or not.
     Savage,     // Various savages that will probably try to eat you. Can be human, but not Gen.
@@ -92,29 +118,62 @@ impl std::str::FromStr for MobType {
 }
 */
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 // Enemies & NPCs.
 pub struct Mob {
     pub mob_type: MobType,
+    pub difficulty: i32, // Scales the XP given for killing it.
+}
+
+#[derive(Display, EnumString, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AIState {
+    Idle,        // Stands still.
+    Wander,      // Walks around at random.
+    Sleep,       // Doesn't move until disturbed.
+    Chase,       // Goes after the player it sees.
+    Investigate, // Goes to where the player was last seen.
+    Flee,        // Runs away from the player.
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+// Mobs belong to the faction of their MobType, and the player to "Player".
+pub struct Faction {
+    pub name: String,
+}
+
+#[derive(EnumString, Debug, Copy, Clone, PartialEq)]
+pub enum Reaction {
+    Hostile,
+    Neutral,
+    Allied,
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+// The mob's current AI state, updated every turn by the HostileAISystem.
+pub struct Behavior {
+    pub state: AIState,
+    pub rest: AIState, // State it goes back to when it loses track of the player.
+    pub flee_hp: i32,  // Flees when its hp is at or below this percentage of max_hp.
+    pub last_seen: Option<Position>,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Name {
     pub name: String,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Description {
     pub descr: String,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct InventoryCapacity {
     pub max: u8,
     pub curr: u8,
 }
 
-#[derive(Component, PartialEq)]
+#[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
 // An entity's field of view (fov).
 pub struct Fov {
     pub range: i32,
@@ -122,37 +181,237 @@ pub struct Fov {
     pub dirty: bool,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 // Entities with this component will "block" movement over them.
 // After all, you can't walk over enemies (unless you're flying!).
 pub struct Blocker {}
 
-#[derive(Component, Debug)]
+#[derive(
+    Display, EnumString, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum DamageType {
+    Slashing,
+    Piercing,
+    #[default]
+    Blunt,
+    Ballistic,
+    Fire,
+    Cold,
+    Acid,
+    Psychic,
+    Poison,
+    Bleeding,
+    Hunger,
+    Thirst,
+}
+
+impl DamageType {
+    /// Damage from lacking food or water, which no armor protects against.
+    pub fn is_deprivation(self) -> bool {
+        matches!(self, DamageType::Hunger | DamageType::Thirst)
+    }
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Attack {
-    pub base_damage: String,
-    pub dice_n: i32,
-    pub dice_faces: i32,
-    pub dice_bonus: i32,
+    pub damage: Dice,
     pub range: i32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub on_hit: Option<OnHit>,
+}
+
+/// A status effect an attack may inflict when it hits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OnHit {
+    pub status: StatusType,
+    pub turns: i32,
+    pub chance: i32, // %
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Health {
     pub max_hp: i32,
     pub hp: i32,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct BaseStats {
     pub health: Health,
     pub defense: i32,
     pub attack: Attack,
+    #[serde(default)]
+    pub attack_bonus: i32, // Added to the damage of every melee or missile hit.
+    #[serde(default)]
+    pub attributes: Attributes,
     pub god: bool, // Doesn't die
 }
 
-#[derive(Component)]
+pub const BASE_ATTRIBUTE: i32 = 5; // An average creature's, giving no bonus nor penalty.
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Attributes {
+    pub strength: i32,   // Melee damage.
+    pub agility: i32,    // Melee accuracy, dodging melee attacks and reload speed.
+    pub perception: i32, // Missile accuracy.
+}
+
+impl Default for Attributes {
+    fn default() -> Self {
+        Attributes {
+            strength: BASE_ATTRIBUTE,
+            agility: BASE_ATTRIBUTE,
+            perception: BASE_ATTRIBUTE,
+        }
+    }
+}
+
+impl Attributes {
+    /// Bonus (or penalty, if negative) given by an attribute's value.
+    pub fn bonus(value: i32) -> i32 {
+        value - BASE_ATTRIBUTE
+    }
+}
+
+const PRACTICE_PER_LEVEL: i32 = 5; // Uses to go from level n to n+1: n+1 times this.
+const MAX_SKILL_LEVEL: i32 = 10;
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Skill {
+    pub level: i32,
+    pub practice: i32, // Uses since the last level.
+}
+
+impl Skill {
+    /// Counts one more use of the skill. Returns true if it improved.
+    pub fn train(&mut self) -> bool {
+        if self.level >= MAX_SKILL_LEVEL {
+            return false;
+        }
+        self.practice += 1;
+        if self.practice >= PRACTICE_PER_LEVEL * (self.level + 1) {
+            self.level += 1;
+            self.practice = 0;
+            return true;
+        }
+
+        false
+    }
+}
+
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+// Weapon skills, improved by fighting with each class of weapon.
+pub struct Skills {
+    pub melee: HashMap<MeleeWeaponClass, Skill>,
+    pub missile: HashMap<MissileWeaponClass, Skill>,
+}
+
+impl Skills {
+    pub fn melee_level(&self, class: MeleeWeaponClass) -> i32 {
+        self.melee.get(&class).map_or(0, |s| s.level)
+    }
+
+    pub fn missile_level(&self, class: MissileWeaponClass) -> i32 {
+        self.missile.get(&class).map_or(0, |s| s.level)
+    }
+
+    /// Counts a use of a melee weapon of the class. Returns the new level if the skill improved.
+    pub fn train_melee(&mut self, class: MeleeWeaponClass) -> Option<i32> {
+        let skill = self.melee.entry(class).or_default();
+        if skill.train() {
+            Some(skill.level)
+        } else {
+            None
+        }
+    }
+
+    /// Counts a use of a missile weapon of the class. Returns the new level if the skill improved.
+    pub fn train_missile(&mut self, class: MissileWeaponClass) -> Option<i32> {
+        let skill = self.missile.entry(class).or_default();
+        if skill.train() {
+            Some(skill.level)
+        } else {
+            None
+        }
+    }
+}
+
+const XP_PER_LEVEL: i32 = 20; // Reaching level n+1 takes n times this much more XP than level n.
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: i32,
+    pub xp: i32, // Total, not reset on level up.
+}
+
+impl Experience {
+    /// Total XP needed to reach the next level.
+    pub fn next_level_xp(&self) -> i32 {
+        XP_PER_LEVEL * self.level * (self.level + 1) / 2
+    }
+
+    pub fn can_level_up(&self) -> bool {
+        self.xp >= self.next_level_xp()
+    }
+}
+
+/// How the player's run went, for the death screen and the morgue file.
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    pub turns: i32,
+    pub kills: BTreeMap<String, i32>, // By mob type.
+    pub cause_of_death: Option<String>,
+}
+
+pub const SURVIVAL_MAX: i32 = 1000; // A full meter: sated, quenched or rested.
+pub const SURVIVAL_LOW: i32 = 250; // Below this, a meter carries penalties.
+const LOW_METER_PENALTY: i32 = 10; // To hit (%), for each meter running low.
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+// The player's survival meters, drained every turn from SURVIVAL_MAX down to 0.
+pub struct Survival {
+    pub hunger: i32,
+    pub thirst: i32,
+    pub fatigue: i32,
+    pub resting: bool, // Recovering from fatigue, a turn at a time.
+}
+
+impl Default for Survival {
+    fn default() -> Self {
+        Self {
+            hunger: SURVIVAL_MAX,
+            thirst: SURVIVAL_MAX,
+            fatigue: SURVIVAL_MAX,
+            resting: false,
+        }
+    }
+}
+
+impl Survival {
+    /// Chance (%) to hit lost to hunger, thirst and fatigue.
+    pub fn hit_penalty(&self) -> i32 {
+        let low = [self.hunger, self.thirst, self.fatigue]
+            .iter()
+            .filter(|&&meter| meter < SURVIVAL_LOW)
+            .count();
+
+        low as i32 * LOW_METER_PENALTY
+    }
+}
+
+/// The stat raised when leveling up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LevelUpChoice {
+    Health,
+    Defense,
+    Attack,
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct SufferDamage {
-    pub amount: Vec<(i32, bool)>,
+    pub amount: Vec<(i32, DamageType, bool, String)>, // (damage, type, from player, source)
 }
 
 impl SufferDamage {
@@ -160,14 +419,15 @@ impl SufferDamage {
         dmg_store: &mut WriteStorage<SufferDamage>,
         victim: Entity,
         amount: i32,
+        damage_type: DamageType,
         from_player: bool,
+        source: &str, // What dealt it, e.g. "Drifter's Revolver".
     ) {
+        let hit = (amount, damage_type, from_player, source.to_string());
         if let Some(suffering) = dmg_store.get_mut(victim) {
-            suffering.amount.push((amount, from_player));
+            suffering.amount.push(hit);
         } else {
-            let dmg = SufferDamage {
-                amount: vec![(amount, from_player)],
-            };
+            let dmg = SufferDamage { amount: vec![hit] };
             dmg_store
                 .insert(victim, dmg)
                 .expect("Unable to insert damage");
@@ -175,44 +435,123 @@ impl SufferDamage {
     }
 }
 
-#[derive(Component)]
+#[derive(Display, EnumString, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+pub enum StatusType {
+    Bleeding,
+    Poisoned,
+    Stunned,
+    Burning,
+}
+
+impl StatusType {
+    /// Damage dealt every turn the status lasts.
+    pub fn damage(self) -> Option<(i32, DamageType)> {
+        match self {
+            StatusType::Bleeding => Some((1, DamageType::Bleeding)),
+            StatusType::Poisoned => Some((1, DamageType::Poison)),
+            StatusType::Burning => Some((2, DamageType::Fire)),
+            StatusType::Stunned => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub status: StatusType,
+    pub turns: i32,
+    pub from_player: bool,
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+// Timed effects, ticked on the owner's turn by the StatusSystem.
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    /// Applies the status to the victim. An already active status lasts for the longest of both.
+    pub fn add_status(
+        status_store: &mut WriteStorage<StatusEffects>,
+        victim: Entity,
+        status: StatusType,
+        turns: i32,
+        from_player: bool,
+    ) {
+        let effect = StatusEffect {
+            status,
+            turns,
+            from_player,
+        };
+        if let Some(statuses) = status_store.get_mut(victim) {
+            match statuses.effects.iter_mut().find(|e| e.status == status) {
+                Some(active) => {
+                    active.turns = i32::max(active.turns, turns);
+                    active.from_player |= from_player;
+                }
+                None => statuses.effects.push(effect),
+            }
+        } else {
+            status_store
+                .insert(
+                    victim,
+                    StatusEffects {
+                        effects: vec![effect],
+                    },
+                )
+                .expect("Unable to insert status effect");
+        }
+    }
+
+    pub fn has(&self, status: StatusType) -> bool {
+        self.effects.iter().any(|e| e.status == status)
+    }
+}
+
+#[derive(Component, Clone, ConvertSaveload)]
 pub struct MeleeAttack {
     pub target: Entity,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, ConvertSaveload)]
 pub struct MissileAttack {
     pub target: Entity,
 }
 
-#[derive(Debug)]
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct ThrowAttack {
+    pub target: Position,
+}
+
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MeleeWeaponClass {
     Dagger,
     Sword,
     Axe,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct MeleeWeapon {
     pub stats: Attack,
     pub class: MeleeWeaponClass,
 }
 
-#[derive(Debug, EnumString, PartialEq)]
+#[derive(Debug, EnumString, PartialEq, Clone, Serialize, Deserialize)]
 pub enum AmmoType {
     Arrow,
     _32,
     _9mm,
+    Grenade,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Ammunition {
     pub max_ammo: i32,
     pub ammo: i32,
     pub ammo_type: AmmoType,
 }
 
-#[derive(Debug)]
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MissileWeaponClass {
     Pistol, // includes Revolvers
     Rifle,
@@ -220,27 +559,43 @@ pub enum MissileWeaponClass {
     Grenade,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct MissileWeapon {
     pub stats: Attack,
     pub class: MissileWeaponClass,
     pub ammo: Ammunition,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
+// Missile weapons that blow up where they land instead of hitting a single target.
+pub struct Explosive {
+    pub radius: i32,
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct ActiveWeapon {}
 
-#[derive(Component)]
+#[derive(Component, Clone, ConvertSaveload)]
 pub struct TryReload {
     pub weapon: Entity,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+// A shot in flight. Only drawn (on the sparse console) until it reaches the end of its path.
+pub struct Projectile {
+    pub path: Vec<Point>,
+    pub glyph: u16,
+    pub fg: RGBA,
+    pub elapsed: f32, // ms
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Target {
     pub covered: bool,
+    pub hit_chance: i32, // %, shown while targeting.
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum EquipSlot {
     Weapon1,
     Weapon2,
@@ -253,40 +608,47 @@ pub enum EquipSlot {
     Floating,
 }
 
-#[derive(Component, Debug, PartialEq)]
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Equipable {
     pub slot: EquipSlot,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, ConvertSaveload)]
 pub struct Equipment {
     pub user: Entity,
     pub equip: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, ConvertSaveload)]
 pub struct TryEquip {
     pub equipment: Equipment,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, ConvertSaveload)]
 pub struct TryUnequip {
     pub equipment: Equipment,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Armor {
     pub defense: i32,
+    // Damage taken of each type is reduced by these amounts, or increased if negative.
+    pub resistances: HashMap<DamageType, i32>,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Item {
     pub tier: u8,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Consumable {
     pub heal: i32,
+    pub statuses: Vec<(StatusType, i32)>, // (status, turns)
+    #[serde(default)]
+    pub food: i32,   // Hunger restored.
+    #[serde(default)]
+    pub drink: i32,  // Thirst restored.
 }
 
 #[derive(Component, Debug, Clone)]
@@ -313,44 +675,104 @@ impl CollectItem {
     }
 }
 
-#[derive(Component, Debug, Clone)]
+// The derive macro can't handle tuples of entities, so CollectItem converts its pairs by hand.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound = "M: Marker")]
+pub struct CollectItemData<M> {
+    pub collects: Vec<(M, M)>,
+}
+
+impl<M: Marker + Serialize> ConvertSaveload<M> for CollectItem
+where
+    for<'de> M: Deserialize<'de>,
+{
+    type Data = CollectItemData<M>;
+    type Error = Infallible;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        let collects = self
+            .collects
+            .iter()
+            .map(|(item, collector)| (ids(*item).unwrap(), ids(*collector).unwrap()))
+            .collect();
+        Ok(CollectItemData { collects })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        let collects = data
+            .collects
+            .into_iter()
+            .map(|(item, collector)| (ids(item).unwrap(), ids(collector).unwrap()))
+            .collect();
+        Ok(CollectItem { collects })
+    }
+}
+
+#[derive(Component, Debug, Clone, ConvertSaveload)]
 pub struct DropItem {
     pub dropper: Entity,
     pub item: Entity,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, ConvertSaveload)]
 pub struct ConsumeItem {
     pub target: Entity,
     pub item: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, ConvertSaveload)]
 pub struct Inventory {
     pub owner: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, ConvertSaveload)]
 pub struct SelectedItem {
     pub item: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct SelectedPosition {
     pub pos: Position,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Remains {} // The remains of a dead mob.
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Container {
     pub tiers: Vec<u8>,
     pub max_items: u8,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, ConvertSaveload)]
 pub struct Contained {
     // Similar to Inventory, but specifically for containers.
     pub container: Entity,
 }
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct OtherLevelPosition {
+    // Position of an entity parked on a level the player isn't on.
+    pub pos: Position,
+    pub level: usize,
+}
+
+// Entities with this marker are written to the save file.
+pub struct SerializeMe;
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+// Temporary entity that carries the resources (maps, log, state) in and out of a save file.
+pub struct SerializationHelper {
+    pub map: Map,
+    pub maps: Vec<Map>,
+    pub log: Vec<(String, RGBA)>,
+    pub runstate: RunState,
+    pub seed: RunSeed,
+    pub rng: RandomNumberGenerator,
+}
@@ -18,6 +18,12 @@ use bracket_lib::prelude::*;
 pub fn player_input(gs: &mut State, term: &mut BTerm) -> RunState {
     //let input_mutex = &INPUT.lock();
     //let lshift_down = input_mutex.is_key_pressed(VirtualKeyCode::LShift);
+    if player_stunned(&mut gs.ecs) {
+        return RunState::PlayerTurn;
+    }
+    if player_resting(&gs.ecs) {
+        return keep_resting(&mut gs.ecs, term.key.is_some());
+    }
     match term.key {
         None => return RunState::Waiting,
         Some(key) => match key {
@@ -72,9 +78,18 @@ pub fn player_input(gs: &mut State, term: &mut BTerm) -> RunState {
 
             VirtualKeyCode::Space => return context_action(&mut gs.ecs),
 
+            // Rest, to recover from fatigue.
+            VirtualKeyCode::S => return rest(&mut gs.ecs),
+
             // Wait (skip turn).
             VirtualKeyCode::Period => return RunState::PlayerTurn,
 
+            // Save and go back to the main menu.
+            VirtualKeyCode::Escape => {
+                term.key = None;
+                return RunState::SaveGame;
+            }
+
             _ => return RunState::Waiting,
         },
     }
@@ -106,6 +121,40 @@ pub fn targeting_input(gs: &mut State, term: &mut BTerm) -> RunState {
     RunState::PlayerTurn
 }
 
+/// Valid inputs while aiming a thrown explosive at a tile.
+pub fn aim_area_input(gs: &mut State, term: &mut BTerm, target: Point, radius: i32) -> RunState {
+    let ecs = &gs.ecs;
+    match term.key {
+        None => RunState::AimArea { target, radius },
+        Some(key) => match key {
+            VirtualKeyCode::L | VirtualKeyCode::Numpad6 | VirtualKeyCode::Right => {
+                move_aim(ecs, target, radius, EAST)
+            }
+            VirtualKeyCode::H | VirtualKeyCode::Numpad4 | VirtualKeyCode::Left => {
+                move_aim(ecs, target, radius, WEST)
+            }
+            VirtualKeyCode::K | VirtualKeyCode::Numpad8 | VirtualKeyCode::Up => {
+                move_aim(ecs, target, radius, NORTH)
+            }
+            VirtualKeyCode::J | VirtualKeyCode::Numpad2 | VirtualKeyCode::Down => {
+                move_aim(ecs, target, radius, SOUTH)
+            }
+            VirtualKeyCode::U | VirtualKeyCode::Numpad9 => move_aim(ecs, target, radius, NORTHEAST),
+            VirtualKeyCode::Y | VirtualKeyCode::Numpad7 => move_aim(ecs, target, radius, NORTHWEST),
+            VirtualKeyCode::N | VirtualKeyCode::Numpad3 => move_aim(ecs, target, radius, SOUTHEAST),
+            VirtualKeyCode::B | VirtualKeyCode::Numpad1 => move_aim(ecs, target, radius, SOUTHWEST),
+
+            // Throw.
+            VirtualKeyCode::F | VirtualKeyCode::Return => throw_attack(ecs, target),
+
+            // Cancel aiming.
+            VirtualKeyCode::Escape => RunState::Waiting,
+
+            _ => RunState::AimArea { target, radius },
+        },
+    }
+}
+
 /// Valid inputs while in Targeting mode.
 pub fn action_dir_input(gs: &mut State, term: &mut BTerm) -> RunState {
     let ppos = **(&gs.ecs.fetch::<Point>());
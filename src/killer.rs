@@ -1,4 +1,7 @@
-use super::{log::Log, spawner::spawn_remains, BaseStats, Inventory, Name, Player, Position};
+use super::{
+    common::is_seen_by, log::Log, spawner::spawn_remains, BaseStats, Fov, Inventory, Name, Player,
+    Position, StatusEffects,
+};
 use crate::utils::colors::*;
 use specs::prelude::*;
 
@@ -15,32 +18,40 @@ pub struct Killer<'a> {
     pub ecs: &'a mut World,
 }
 
-/// Remove all the dead entities from the ECS.
-pub fn remove_dead_entities(ecs: &mut World) {
+/// Remove all the dead entities from the ECS. Returns whether the player is dead (the player's
+/// entity is kept, for the death screen).
+pub fn remove_dead_entities(ecs: &mut World) -> bool {
     Killer { ecs }.kill_all()
 }
 
 impl<'a> Killer<'a> {
-    pub fn kill_all(&mut self) {
+    pub fn kill_all(&mut self) -> bool {
         let mut dead: Vec<(Entity, String, Position)> = Vec::new();
+        let mut player_dead = false;
         {
+            let mut status_effects = self.ecs.write_storage::<StatusEffects>();
             let entities = self.ecs.entities();
             let stats = self.ecs.read_storage::<BaseStats>();
             let names = self.ecs.read_storage::<Name>();
             let positions = self.ecs.read_storage::<Position>();
             let player = self.ecs.read_storage::<Player>();
+            let fov = self.ecs.read_storage::<Fov>();
+            let player_ent = self.ecs.fetch::<Entity>();
             let mut log = self.ecs.fetch_mut::<Log>();
 
-            let red = color("BrightRed", 1.0);
             let yellow = color("BrightYellow", 1.0);
 
             for (ent, stats, name, pos) in (&entities, &stats, &names, &positions).join() {
                 if stats.health.hp <= 0 {
+                    // The dead don't bleed anymore.
+                    status_effects.remove(ent);
                     let p: Option<&Player> = player.get(ent);
                     if let Some(_p) = p {
-                        log.add("You died...", red);
+                        player_dead = true;
                     } else {
-                        log.add(format!("{} dies.", &name.name), yellow);
+                        if is_seen_by(ent, *player_ent, &positions, &fov) {
+                            log.add(format!("{} dies.", &name.name), yellow);
+                        }
                         dead.push((ent, name.name.to_string(), *pos));
                     }
                 }
@@ -52,6 +63,8 @@ impl<'a> Killer<'a> {
                 .delete_entity(f.0)
                 .expect("Unable to remove the dead");
         }
+
+        player_dead
     }
 
     #[allow(unused)]
@@ -0,0 +1,46 @@
+/*
+ *
+ * lib.rs
+ * ------
+ * The game's modules, shared by the game itself (main.rs) and the tools in src/bin.
+ *
+ */
+
+use bracket_lib::prelude::*;
+
+pub mod common;
+pub mod state;
+use state::RunState;
+pub mod components;
+pub use components::*;
+pub mod input;
+pub mod killer;
+pub mod log;
+pub mod map_gen;
+pub mod morgue;
+pub mod player;
+pub mod raws;
+pub mod renderer;
+pub mod rexloader;
+pub mod saveload;
+pub use raws::load_raws;
+pub mod setup;
+pub mod spawner;
+pub mod systems;
+pub mod ui;
+pub mod utils;
+
+#[macro_use]
+extern crate lazy_static;
+
+pub const X_OFFSET: i32 = 20; // Left box
+pub const Y_OFFSET: i32 = 10; // Bottom box
+pub const WINDOW_WIDTH: i32 = 80;
+pub const WINDOW_HEIGHT: i32 = 60;
+//pub const WINDOW_HEIGHT: i32 = 80+Y_OFFSET;
+pub const MAP_WIDTH: i32 = 80;
+pub const MAP_HEIGHT: i32 = 60;
+pub const TILE_WIDTH: i32 = 18;
+pub const TILE_HEIGHT: i32 = 18;
+
+pub const SHOW_MAP: bool = false;
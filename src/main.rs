@@ -1,42 +1,26 @@
 use bracket_lib::prelude::*;
-use specs::prelude::*;
-
-mod common;
-mod state;
-use state::{RunState, State};
-mod components;
-pub use components::*;
-mod input;
-mod killer;
-mod log;
-mod map_gen;
-use map_gen::TileType;
-mod player;
-mod raws;
-mod renderer;
-mod rexloader;
-pub use raws::load_raws;
-mod spawner;
-mod systems;
-mod ui;
-mod utils;
-
-#[macro_use]
-extern crate lazy_static;
-
-pub const X_OFFSET: i32 = 20; // Left box
-pub const Y_OFFSET: i32 = 10; // Bottom box
-pub const WINDOW_WIDTH: i32 = 80;
-pub const WINDOW_HEIGHT: i32 = 60;
-//pub const WINDOW_HEIGHT: i32 = 80+Y_OFFSET;
-pub const TILE_WIDTH: i32 = 18;
-pub const TILE_HEIGHT: i32 = 18;
-
-pub const SHOW_MAP: bool = false;
+use tcc_ufsm_2020::{
+    raws, rexloader,
+    state::{RunState, State},
+    SHOW_MAP, TILE_HEIGHT, TILE_WIDTH, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
 
 embedded_resource!(FONT01, "../resources/fonts/Sapphos-square-16x16.png");
 embedded_resource!(FONT02, "../resources/fonts/Anikki-square-16x16.png");
 
+/// Reads the run seed (hexadecimal) from `--seed <seed>`, so a run can be reproduced.
+/// Without it, a random seed is used.
+fn get_seed() -> u64 {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--seed") {
+        match args.get(i + 1).map(|s| u64::from_str_radix(s, 16)) {
+            Some(Ok(seed)) => return seed,
+            _ => eprintln!("Invalid seed, using a random one."),
+        }
+    }
+    RandomNumberGenerator::new().next_u64()
+}
+
 fn main() -> BError {
     link_resource!(FONT01, "resources/Sapphos-square-16x16.png");
     link_resource!(FONT02, "resources/Anikki-square-16x16.png");
@@ -58,79 +42,11 @@ fn main() -> BError {
     rexloader::load_dungeons();
     raws::load_raws();
 
-    let mut world = World::new();
-
-    // Register the components (see components.rs).
-    world.register::<Position>();
-    world.register::<Renderable>();
-    world.register::<Player>();
-    world.register::<Mob>();
-    world.register::<Name>();
-    world.register::<Description>();
-    world.register::<InventoryCapacity>();
-    world.register::<Fov>();
-    world.register::<Blocker>();
-    world.register::<Health>();
-    world.register::<Attack>();
-    world.register::<BaseStats>();
-    world.register::<SufferDamage>();
-    world.register::<MeleeAttack>();
-    world.register::<MissileAttack>();
-    world.register::<MeleeWeapon>();
-    world.register::<MissileWeapon>();
-    world.register::<Ammunition>();
-    world.register::<ActiveWeapon>();
-    world.register::<TryReload>();
-    world.register::<Target>();
-    world.register::<Equipable>();
-    world.register::<Equipment>();
-    world.register::<TryEquip>();
-    world.register::<TryUnequip>();
-    world.register::<Item>();
-    world.register::<Armor>();
-    world.register::<Consumable>();
-    world.register::<CollectItem>();
-    world.register::<DropItem>();
-    world.register::<ConsumeItem>();
-    world.register::<Inventory>();
-    world.register::<SelectedItem>();
-    world.register::<SelectedPosition>();
-    world.register::<Remains>();
-    world.register::<Container>();
-    world.register::<Contained>();
-
-    // Create game state.
-    let mut game_state = State::new(world);
-
-    // Insert map into the ECS and generate it.
-    let (height, width) = (80, 60);
-
-    game_state
-        .ecs
-        .insert(map_gen::Map::new(height, width, TileType::Floor, None));
-
-    game_state.ecs.insert(RandomNumberGenerator::new());
-
-    game_state.generate_new_map(height, width);
-    game_state.ecs.insert(Point::new(0, 0));
-    let player = spawner::create_player(&mut game_state.ecs);
-    game_state.ecs.insert(player);
-
-    spawner::equip_player(&mut game_state.ecs);
-    game_state.populate_map();
-
-    // Insert initial state into the ECS.
-    //game_state.ecs.insert(RunState::Start);
-    if !SHOW_MAP {
-        game_state.ecs.insert(RunState::Menu {
-            menu_selection: ui::menu::MenuSelection::NewGame,
-        });
-    } else {
+    // Create game state, with a new run.
+    let mut game_state = State::new(get_seed());
+    if SHOW_MAP {
         game_state.ecs.insert(RunState::Start);
     }
 
-    // Insert the Log into the ECS.
-    game_state.ecs.insert(log::Log::new());
-
     bracket_lib::prelude::main_loop(term, game_state)
 }
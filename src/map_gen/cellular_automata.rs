@@ -4,6 +4,7 @@ use super::{
     region::Operations,
     CustomRegion, Map, Point, Tile, TileType,
 };
+use bracket_lib::prelude::RandomNumberGenerator;
 
 /*
  *
@@ -46,7 +47,7 @@ impl<'a> CellularAutomata<'a> {
         }
     }
 
-    pub fn generate(&mut self, map: &mut Map) {
+    pub fn generate(&mut self, map: &mut Map, rng: &mut RandomNumberGenerator) {
         // We need to make a clone here because the already replaced cells MUST NOT
         // affect the current cell.
         let mut tiles = map.tiles.clone();
@@ -110,7 +111,7 @@ impl<'a> CellularAutomata<'a> {
         //main_caves.sort_by(|a, b| a[0].cmp(&b[0]));
         // change below to .y for mostly horizontal tunnels
         main_caves.sort_by(|a, b| map.idx_pos(a[0]).x.cmp(&map.idx_pos(b[0]).x));
-        connect_regions(map, main_caves, TileType::Floor, true);
+        connect_regions(map, main_caves, TileType::Floor, true, rng);
         self.smooth_map(map);
     }
 
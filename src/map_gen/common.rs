@@ -180,6 +180,7 @@ pub fn make_exact_tunnel(
     y2: i32,
     ttype: TileType,
     natural: bool,
+    rng: &mut RandomNumberGenerator,
 ) {
     let mut x = x1;
     let mut y = y1;
@@ -202,7 +203,6 @@ pub fn make_exact_tunnel(
             map.paint_tile(idx, ttype);
 
             if natural {
-                let mut rng = RandomNumberGenerator::new();
                 let sign_x = rng.range(0, 3);
                 let sign_y = rng.range(0, 3);
                 let add_x = if sign_x < 1 { 1 } else { -1 };
@@ -230,9 +230,13 @@ pub fn make_exact_tunnel(
 }
 
 #[allow(dead_code)]
-pub fn make_lake(map: &mut Map, region: &CustomRegion, liquid: TileType, total_tiles: u32) {
-    let mut rng = RandomNumberGenerator::new();
-
+pub fn make_lake(
+    map: &mut Map,
+    region: &CustomRegion,
+    liquid: TileType,
+    total_tiles: u32,
+    rng: &mut RandomNumberGenerator,
+) {
     let x = rng.range(region.x1, region.x2);
     let y = rng.range(region.y1, region.y2);
 
@@ -255,7 +259,7 @@ pub fn make_lake(map: &mut Map, region: &CustomRegion, liquid: TileType, total_t
                     map.tiles[idx - 1] = Tile::shallow_water();
                 }
             }
-            let dir = get_random_dir();
+            let dir = get_random_dir(rng);
             walker_pos += dir;
             n_tiles += 1;
         }
@@ -312,8 +316,12 @@ pub fn count_neighbor_tile_entity(
 }
 
 #[allow(dead_code)]
-pub fn add_vegetation(map: &mut Map, region: &CustomRegion, trees: bool) {
-    let mut rng = RandomNumberGenerator::new();
+pub fn add_vegetation(
+    map: &mut Map,
+    region: &CustomRegion,
+    trees: bool,
+    rng: &mut RandomNumberGenerator,
+) {
     for y in region.y1..region.y2 {
         for x in region.x1..region.x2 {
             let idx = map.idx(x, y);
@@ -412,7 +420,13 @@ pub fn get_region(start_idx: usize, map: &Map) -> Region {
 }
 
 /// Connects with tunnels the selected regions.
-pub fn connect_regions(map: &mut Map, regions: Vec<Region>, ttype: TileType, natural: bool) {
+pub fn connect_regions(
+    map: &mut Map,
+    regions: Vec<Region>,
+    ttype: TileType,
+    natural: bool,
+    rng: &mut RandomNumberGenerator,
+) {
     if regions.len() <= 1 {
         return;
     }
@@ -462,15 +476,19 @@ pub fn connect_regions(map: &mut Map, regions: Vec<Region>, ttype: TileType, nat
             other_region[other_idx].y,
             ttype,
             natural,
+            rng,
         );
     }
 }
 
 /// Makes map chaotic with a chance of floor_chance to change a tile to floor.
 /// Used in mapgen algorithms that require a "chaotic map" like Cellular Automata.
-pub fn make_chaotic(map: &mut Map, region: &CustomRegion, floor_chance: u8) {
-    let mut rng = RandomNumberGenerator::new();
-
+pub fn make_chaotic(
+    map: &mut Map,
+    region: &CustomRegion,
+    floor_chance: u8,
+    rng: &mut RandomNumberGenerator,
+) {
     for pt in region.pos.iter() {
         let idx = map.idx_pt(*pt);
         if rng.range(1, 101) <= floor_chance {
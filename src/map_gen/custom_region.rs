@@ -1,8 +1,9 @@
 use super::common::{circular_region, rect_region};
 use crate::components::Position;
 use bracket_lib::prelude::DistanceAlg;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CustomRegion {
     pub pos: Vec<Position>,
     pub x1: i32,
@@ -70,4 +71,14 @@ impl CustomRegion {
     pub fn get_center(&self) -> Position {
         Position::new((self.x1 + self.x2) / 2, (self.y1 + self.y2) / 2)
     }
+
+    /// Checks if the region is non-empty and lies entirely inside a `width`x`height` map.
+    pub fn fits_in(&self, width: i32, height: i32) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.x1 >= 0
+            && self.y1 >= 0
+            && self.x2 <= width
+            && self.y2 <= height
+    }
 }
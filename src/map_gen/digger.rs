@@ -56,10 +56,10 @@ impl<'a> Digger<'a> {
     fn add_feature(&mut self, map: &mut Map, room: Room, rng: &mut RandomNumberGenerator) -> bool {
         let w = rng.range(self.min_size, self.max_size);
         let h = rng.range(w, self.max_size);
-        let dir = get_random_dir();
+        let dir = get_random_dir(rng);
         let ndir = dir.clone();
 
-        let mut pt = room.get_wall(map, dir);
+        let mut pt = room.get_wall(map, dir, rng);
         let room_gap = rng.range(3, 10);
 
         match ndir {
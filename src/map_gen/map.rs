@@ -2,6 +2,7 @@ use super::{get_tile_function, CustomRegion, Tile, TileType};
 use crate::components::Position;
 use crate::utils::directions::*;
 use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
 use specs::prelude::Entity;
 use strum_macros::Display;
 
@@ -13,7 +14,7 @@ use strum_macros::Display;
  *
  */
 
-#[derive(Display, Debug, Copy, Clone)]
+#[derive(Display, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum MapType {
     //Forest,
     Ruins,
@@ -22,7 +23,7 @@ pub enum MapType {
     //Town,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<Tile>,
     pub region: CustomRegion,
@@ -30,9 +31,12 @@ pub struct Map {
     pub width: i32,
     pub height: i32,
     pub maptype: Option<MapType>,
-    pub entities: Vec<Option<Vec<Entity>>>,
+    #[serde(skip)]
+    pub entities: Vec<Option<Vec<Entity>>>, // Rebuilt by the MappingSystem after loading.
     pub spawn_point: Position,
     pub exit_point: Position,
+    #[serde(default)]
+    pub level: usize, // Index in MapGenerator.maps.
 }
 
 #[allow(dead_code)]
@@ -50,6 +54,7 @@ impl Map {
             entities: vec![None; map_size as usize],
             spawn_point: Position::new(-1, -1),
             exit_point: Position::new(-1, -1),
+            level: 0,
         }
     }
 
@@ -219,6 +224,9 @@ impl Map {
             '>' => {
                 self.tiles[idx] = Tile::exit();
             }
+            '<' => {
+                self.tiles[idx] = Tile::exit_up();
+            }
             _ => {
                 self.tiles[idx] = Tile::floor();
             }
@@ -250,6 +258,7 @@ impl Map {
             TileType::Floor2 => true,
             TileType::WoodenFloor => true,
             TileType::Path1 => true,
+            TileType::Ashes => true,
             _ => false,
         }
     }
@@ -297,6 +306,14 @@ impl Map {
         }
     }
 
+    pub fn is_exit_up(&self, idx: usize) -> bool {
+        let ttype = self.tiles[idx].ttype;
+        match ttype {
+            TileType::ExitUp => true,
+            _ => false,
+        }
+    }
+
     pub fn is_visible(&self, idx: usize) -> bool {
         self.tiles[idx].visible
     }
@@ -384,6 +401,31 @@ impl Map {
         }
     }
 
+    /// Checks if a missile shot from `from` can reach `to`, i.e. there's no blocker in between.
+    pub fn line_of_fire(&self, from: Point, to: Point) -> bool {
+        self.cover_between(from, to) == Some(0)
+    }
+
+    /// Counts the blockers (mobs, furniture, etc.) a missile shot from `from` to `to` would have
+    /// to get past. None if there's an opaque tile in the way, which can't be shot through.
+    pub fn cover_between(&self, from: Point, to: Point) -> Option<i32> {
+        let mut cover = 0;
+        for pt in line2d_bresenham(from, to)
+            .iter()
+            .filter(|pt| **pt != from && **pt != to)
+        {
+            let idx = self.idx_pt(*pt);
+            if self.is_opaque(idx) {
+                return None;
+            }
+            if self.tiles[idx].block {
+                cover += 1;
+            }
+        }
+
+        Some(cover)
+    }
+
     fn valid_exit(&self, loc: Point, delta: Point) -> Option<usize> {
         let destination = loc + delta;
         let idx = self.point2d_to_index(destination);
@@ -1,9 +1,9 @@
 use super::{Point, Position};
-use crate::raws::RAWS;
+use crate::raws::{LevelRecipe, LevelStep, Step, LEVELS, RAWS};
 use crate::spawner::*;
-use bracket_lib::prelude::RandomNumberGenerator;
+use bracket_lib::prelude::{DijkstraMap, RandomNumberGenerator};
 use specs::prelude::World;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub mod tile;
 pub use tile::{get_tile_function, Tile, TileType};
@@ -25,39 +25,65 @@ mod prefab_section;
 //use prefab_section::*;
 mod wfc;
 use wfc::*;
+pub use wfc::{doorway_pins, edge_pins, Pin, Symmetry, WfcStats};
 
 mod room;
 use room::*;
 mod tunnel;
 use tunnel::*;
 mod custom_region;
-use custom_region::*;
+pub use custom_region::*;
 mod region;
 use region::*;
+mod validation;
+pub use validation::*;
+
+// Generation attempts of a level before giving up on validation.
+const MAX_GEN_ATTEMPTS: u64 = 5;
+// Unreachable exits repaired on a single attempt.
+const MAX_REPAIRS: usize = 4;
+
+/// SplitMix64 step from `seed`, so that consecutive values of `n` get unrelated seeds.
+fn splitmix64(seed: u64, n: u64) -> u64 {
+    let mut z = seed.wrapping_add(n.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
 
 pub struct MapGenerator {
     pub maps: Vec<Map>,
     pub rooms: Vec<Room>,
-    pub tunnels: HashSet<Tunnel>,
-    pub regions: HashSet<Region>,
+    pub tunnels: BTreeSet<Tunnel>,
+    pub regions: BTreeSet<Region>,
     pub wfc_input: Map,
     pub rng: RandomNumberGenerator,
+    pub seed: u64, // Run seed; each level is generated from its own sub-seed (see level_seed).
+    pub wfc_stats: BTreeMap<String, WfcStats>, // By template, for all the WFC runs so far.
 }
 
 #[allow(dead_code)]
 impl MapGenerator {
-    pub fn new() -> Self {
+    pub fn new(seed: u64) -> Self {
         Self {
             //maps: Map::new(width, height).push(),
             maps: Vec::new(),
             rooms: Vec::new(),
-            tunnels: HashSet::new(),
-            regions: HashSet::new(),
+            tunnels: BTreeSet::new(),
+            regions: BTreeSet::new(),
             wfc_input: Map::new(80, 60, TileType::Floor, None),
-            rng: RandomNumberGenerator::new(),
+            rng: RandomNumberGenerator::seeded(seed),
+            seed,
+            wfc_stats: BTreeMap::new(),
         }
     }
 
+    /// Returns the sub-seed of the level at `idx`, derived from the run seed.
+    /// A level can then be reproduced on its own, regardless of what was generated before it.
+    pub fn level_seed(&self, idx: usize) -> u64 {
+        splitmix64(self.seed, idx as u64 + 1)
+    }
+
     pub fn spawn_entities(&mut self, ecs: &mut World, idx: usize) {
         spawn_player(ecs, &self.maps[idx]);
 
@@ -106,11 +132,24 @@ impl MapGenerator {
             }
         }
         //println!("Spawn list size: {}", spawn_list.len());
+
+        // Nothing is spawned where the player can't get to.
+        let reached = reachable_tiles(&self.maps[idx]);
+        let spawn_count = spawn_list.len();
+        spawn_list.retain(|(i, _name)| reached[*i]);
+        if spawn_list.len() < spawn_count {
+            println!(
+                "Level {}: {} unreachable spawns dropped.",
+                idx,
+                spawn_count - spawn_list.len()
+            );
+        }
         spawn_from_list(ecs, spawn_list, &self.maps[idx], raws, &mut self.rng);
     }
 
     pub fn push_map(&mut self, width: i32, height: i32) {
-        let map = Map::new(width, height, TileType::Wall, None);
+        let mut map = Map::new(width, height, TileType::Wall, None);
+        map.level = self.maps.len();
         self.maps.push(map);
     }
 
@@ -120,7 +159,89 @@ impl MapGenerator {
         self.regions.clear();
     }
 
+    /// Restarts the generator RNG from the sub-seed of the level at `idx`.
+    pub fn seed_level(&mut self, idx: usize) {
+        self.rng = RandomNumberGenerator::seeded(self.level_seed(idx));
+    }
+
+    /// Final touches applied to every generated map.
+    pub fn finish_map(&mut self, idx: usize) {
+        self.maps[idx].add_borders(TileType::InvisibleWall);
+        //self.maps[idx].add_borders(TileType::Wall);
+        self.maps[idx].pretty_walls();
+    }
+
+    /// Places the stairs: a "<" on the spawn point (except on the first level), back to the
+    /// previous level, and a ">" on the walkable tile farthest from it, unless the level already
+    /// has its own exits.
+    pub fn place_exits(&mut self, idx: usize) {
+        let map = &mut self.maps[idx];
+        let spawn_idx = map.idx(map.spawn_point.x, map.spawn_point.y);
+        if idx > 0 {
+            map.paint_tile(spawn_idx, TileType::ExitUp);
+        }
+
+        if let Some(i) = (0..map.tiles.len()).find(|i| map.is_exit(*i)) {
+            let exit = map.idx_pos(i);
+            map.set_exit(exit);
+            return;
+        }
+
+        let dijkstra = DijkstraMap::new(map.width, map.height, &[spawn_idx], &*map, 1000.0);
+        let farthest = (0..map.tiles.len())
+            .filter(|i| map.is_walkable(*i) && dijkstra.map[*i] < std::f32::MAX)
+            .max_by(|a, b| dijkstra.map[*a].partial_cmp(&dijkstra.map[*b]).unwrap());
+        if let Some(i) = farthest {
+            map.paint_tile(i, TileType::Exit);
+            let exit = map.idx_pos(i);
+            map.set_exit(exit);
+        }
+    }
+
     pub fn gen_map(&mut self, idx: usize) {
+        // Generation and spawning of this level only depend on its sub-seed. A level that fails
+        // validation is generated again, from a fresh sub-seed derived from the first one.
+        for attempt in 0..MAX_GEN_ATTEMPTS {
+            let seed = self.attempt_seed(idx, attempt);
+            self.rng = RandomNumberGenerator::seeded(seed);
+            if attempt > 0 {
+                self.reset_map(idx);
+            }
+
+            match self.build_map(idx) {
+                Ok(()) => {
+                    println!("Map generated! (level {}, seed {})", idx, seed);
+                    return;
+                }
+                Err(e) => println!("Level {} is invalid ({}), generating it again.", idx, e),
+            }
+        }
+
+        // Rather than leaving the player on a level with no way forward, fall back to a plain
+        // BSP dungeon: its rooms are always connected.
+        println!(
+            "Level {} is still invalid after {} attempts, falling back to BSP.",
+            idx, MAX_GEN_ATTEMPTS
+        );
+        self.rng = RandomNumberGenerator::seeded(self.attempt_seed(idx, MAX_GEN_ATTEMPTS));
+        self.reset_map(idx);
+        if let Err(e) = self.build_fallback_map(idx) {
+            panic!("Level {} is invalid even as a BSP dungeon ({})!", idx, e);
+        }
+    }
+
+    /// A BSP dungeon over the whole level at `idx`, for when its recipe keeps failing.
+    fn build_fallback_map(&mut self, idx: usize) -> Result<(), MapError> {
+        self.clear_regions_generator();
+        self.maps[idx].set_maptype(MapType::Ruins);
+        self.gen_bsp(idx, None);
+        self.finish_map(idx);
+
+        self.validate_level(idx)
+    }
+
+    /// Generates the level at `idx` and validates it, repairing what can be repaired.
+    fn build_map(&mut self, idx: usize) -> Result<(), MapError> {
         //self.gen_bsp(idx, Some(region));
         //self.gen_bsp(idx, None);
         //self.gen_bsp_ruin(idx, None);
@@ -150,13 +271,99 @@ impl MapGenerator {
         self.generate_next_level(idx);
         //self.gen_wfc(idx, None, "../rex_resources/wfc_15x15.xp", 15, 15, 5);
 
-        self.maps[idx].add_borders(TileType::InvisibleWall);
-        //self.maps[idx].add_borders(TileType::Wall);
-        self.maps[idx].pretty_walls();
+        self.finish_map(idx);
 
         //add_vegetation(&mut self.maps[idx], region, false);
 
-        println!("Map generated!");
+        self.validate_level(idx)
+    }
+
+    /// Validates the spawn and exits of the level at `idx`, repairing what can be repaired.
+    fn validate_level(&mut self, idx: usize) -> Result<(), MapError> {
+        if let Err(e) = validate_spawn(&self.maps[idx]) {
+            println!("Level {}: {}, moving it.", idx, e);
+            self.move_spawn(idx).ok_or(e)?;
+        }
+        self.place_exits(idx);
+
+        for _ in 0..MAX_REPAIRS {
+            match validate_map(&self.maps[idx]) {
+                Err(MapError::UnreachableExit(exit)) => {
+                    println!(
+                        "Level {}: {}, digging a tunnel to it.",
+                        idx,
+                        MapError::UnreachableExit(exit)
+                    );
+                    self.connect_exit(idx, exit)
+                        .ok_or(MapError::UnreachableExit(exit))?;
+                }
+                result => return result,
+            }
+        }
+
+        validate_map(&self.maps[idx])
+    }
+
+    /// Sub-seed of a generation attempt of the level at `idx`. The first attempt uses the level
+    /// sub-seed itself.
+    pub fn attempt_seed(&self, idx: usize, attempt: u64) -> u64 {
+        if attempt == 0 {
+            self.level_seed(idx)
+        } else {
+            splitmix64(self.level_seed(idx), attempt)
+        }
+    }
+
+    /// Throws away the level at `idx`, leaving a blank map to generate it again.
+    fn reset_map(&mut self, idx: usize) {
+        let (width, height) = (self.maps[idx].width, self.maps[idx].height);
+        let mut map = Map::new(width, height, TileType::Wall, None);
+        map.level = idx;
+        self.maps[idx] = map;
+    }
+
+    /// Moves the spawn point to the closest walkable tile. Returns None if there's none.
+    fn move_spawn(&mut self, idx: usize) -> Option<()> {
+        let map = &mut self.maps[idx];
+        let spawn = map.spawn_point;
+        let closest = (0..map.tiles.len())
+            .filter(|i| map.is_walkable(*i) && !map.tiles[*i].block)
+            .min_by_key(|i| {
+                let pt = map.idx_pos(*i);
+                (pt.x - spawn.x).pow(2) + (pt.y - spawn.y).pow(2)
+            })?;
+
+        let new_spawn = map.idx_pos(closest);
+        map.set_spawn(new_spawn);
+        Some(())
+    }
+
+    /// Digs a tunnel from an unreachable exit to the closest tile reachable from the spawn.
+    /// Returns None if there's none.
+    fn connect_exit(&mut self, idx: usize, exit: Position) -> Option<()> {
+        let map = &mut self.maps[idx];
+        let reached = reachable_tiles(map);
+        let closest = (0..map.tiles.len())
+            .filter(|i| reached[*i] && map.is_walkable(*i))
+            .min_by_key(|i| {
+                let pt = map.idx_pos(*i);
+                (pt.x - exit.x).pow(2) + (pt.y - exit.y).pow(2)
+            })?;
+
+        let target = map.idx_pos(closest);
+        make_exact_tunnel(
+            map,
+            exit.x,
+            exit.y,
+            target.x,
+            target.y,
+            TileType::Floor,
+            false,
+            &mut self.rng,
+        );
+        // The tunnel may have gone over the "<".
+        self.place_exits(idx);
+        Some(())
     }
 
     pub fn generate_next_level(&mut self, idx: usize) {
@@ -191,7 +398,12 @@ impl MapGenerator {
         */
 
         //self.wfc_test(idx);
-        self.level_01(idx);
+        let depth = idx as i32 + 1;
+        let recipe = LEVELS.lock().unwrap().get_recipe(depth, &mut self.rng);
+        match recipe {
+            Some(recipe) => self.build_level(idx, &recipe),
+            None => self.level_01(idx),
+        }
         //self.maps[idx].set_maptype(MapType::Ruins);
         //self.maps[idx].set_spawn(Position::new(8, 16));
         //self.wfc_01(idx);
@@ -211,7 +423,16 @@ impl MapGenerator {
 
         let reg1 = &CustomRegion::new_rect(0, 0, self.maps[idx].width, self.maps[idx].height);
 
-        self.gen_wfc(idx, Some(reg1), "resources/wfc_9x9_1.xp", 9, 9, 6, false);
+        self.gen_wfc(
+            idx,
+            Some(reg1),
+            "resources/wfc_9x9_1.xp",
+            9,
+            9,
+            6,
+            false,
+            Symmetry::Mirror,
+        );
     }
 
     /*
@@ -243,21 +464,147 @@ impl MapGenerator {
         //make_lake(&mut self.maps[idx], reg1, TileType::ShallowWater, 200);
 
         let mut cell_automata2 = CellularAutomata::new(reg1, 1, 5, 15, true, true);
-        cell_automata2.generate(&mut self.maps[idx]);
+        cell_automata2.generate(&mut self.maps[idx], &mut self.rng);
 
         let mut all_regions = get_all_regions(&self.maps[idx], &self.maps[idx].get_region());
         all_regions.sort_by(|a, b| self.maps[idx].idx_pos(a[0]).x.cmp(&self.maps[idx].idx_pos(b[0]).x));
-        connect_regions(&mut self.maps[idx], all_regions, TileType::Floor, true);
+        connect_regions(&mut self.maps[idx], all_regions, TileType::Floor, true, &mut self.rng);
     }
     */
 
+    /// Builds the level from a recipe of resources/raws/levels.ron.
+    pub fn build_level(&mut self, idx: usize, recipe: &LevelRecipe) {
+        println!("Level recipe: {}", recipe.name);
+        self.maps[idx].set_maptype(recipe.maptype);
+
+        for step in recipe.steps.iter() {
+            self.run_step(idx, step);
+        }
+
+        let spawn = match recipe.spawn {
+            Some((x, y)) => Position::new(x, y),
+            None => {
+                let map = &self.maps[idx];
+                let walkable: Vec<usize> = (0..map.tiles.len())
+                    .filter(|i| map.is_walkable(*i))
+                    .collect();
+                match self.rng.random_slice_entry(&walkable) {
+                    Some(i) => map.idx_pos(*i),
+                    None => Position::new(1, 1),
+                }
+            }
+        };
+        self.maps[idx].set_spawn(spawn);
+    }
+
+    fn run_step(&mut self, idx: usize, step: &LevelStep) {
+        if let Some(chance) = step.chance {
+            if self.rng.range(0, 100) >= chance {
+                return;
+            }
+        }
+
+        let region = step
+            .region
+            .to_custom_region()
+            .unwrap_or_else(|| self.maps[idx].get_region());
+        let reg = &region;
+
+        match &step.step {
+            Step::Bsp => self.gen_bsp(idx, Some(reg)),
+            Step::BspRuin => self.gen_bsp_ruin(idx, Some(reg)),
+            Step::Cave => self.gen_cave(idx, Some(reg)),
+            Step::TightCave => self.gen_tight_cave(idx, Some(reg)),
+            Step::Forest => self.gen_forest(idx, Some(reg)),
+            Step::Digger => self.gen_digger(idx, Some(reg)),
+            Step::DiggerInverted => self.gen_digger_inverted(idx, Some(reg)),
+            Step::Prefab(template) => {
+                self.gen_prefab_map(idx, &format!("resources/{}.xp", template))
+            }
+            Step::Wfc {
+                template,
+                size,
+                tile_size,
+                mix_match,
+                symmetry,
+            } => self.gen_wfc(
+                idx,
+                Some(reg),
+                &format!("resources/{}.xp", template),
+                size.0,
+                size.1,
+                *tile_size,
+                *mix_match,
+                *symmetry,
+            ),
+            Step::WfcRooms {
+                template,
+                size,
+                tile_size,
+                mix_match,
+                symmetry,
+                room_chance,
+                min_room,
+            } => {
+                let template = format!("resources/{}.xp", template);
+                for room in self.rooms.clone() {
+                    let big_room = match min_room {
+                        Some((w, h)) => room.width() >= *w && room.height() >= *h,
+                        None => false,
+                    };
+                    if big_room || self.rng.range(0, 100) < *room_chance {
+                        let room_reg =
+                            &CustomRegion::new_rect(room.x1, room.y1, room.width(), room.height());
+                        // Keep the tunnels and doorways that reach the room open.
+                        let pins = doorway_pins(&self.maps[idx], room_reg, *tile_size);
+                        self.gen_wfc_pinned(
+                            idx,
+                            Some(room_reg),
+                            &template,
+                            size.0,
+                            size.1,
+                            *tile_size,
+                            *mix_match,
+                            *symmetry,
+                            &pins,
+                        );
+                    }
+                }
+            }
+            Step::OneOf(steps) => {
+                if let Some(step) = self.rng.random_slice_entry(steps) {
+                    self.run_step(idx, &step.clone());
+                }
+            }
+            Step::ConnectRegions { natural } => {
+                let all_regions = get_all_regions(&self.maps[idx], reg);
+                connect_regions(
+                    &mut self.maps[idx],
+                    all_regions,
+                    TileType::Floor,
+                    *natural,
+                    &mut self.rng,
+                );
+            }
+            Step::AddVegetation { trees } => {
+                add_vegetation(&mut self.maps[idx], reg, *trees, &mut self.rng)
+            }
+            Step::AddDoors { chance } => {
+                add_doors(&mut self.maps[idx], &self.rooms, *chance, &mut self.rng)
+            }
+            Step::MakeLake { liquid, tiles } => {
+                make_lake(&mut self.maps[idx], reg, *liquid, *tiles, &mut self.rng)
+            }
+        }
+    }
+
     pub fn level_00(&mut self, idx: usize) {
         self.maps[idx].set_maptype(MapType::Ruins);
         self.maps[idx].set_spawn(Position::new(8, 16));
 
         self.gen_prefab_map(idx, "resources/level01_80x60.xp");
         let reg = &CustomRegion::new_rect(0, 0, self.maps[idx].width, self.maps[idx].height);
-        add_vegetation(&mut self.maps[idx], reg, false);
+        add_vegetation(&mut self.maps[idx], reg, false, &mut self.rng);
 
         self.regions.insert(
             get_all_regions(&self.maps[idx], &reg)
@@ -293,7 +640,18 @@ impl MapGenerator {
             if self.rng.range(0, 4) > 1 || (room.width() >= 6 && room.height() >= 6) {
                 let room_reg =
                     &CustomRegion::new_rect(room.x1, room.y1, room.width(), room.height());
-                self.gen_wfc(idx, Some(room_reg), "resources/wfc_6x6.xp", 9, 9, 2, false);
+                let pins = doorway_pins(&self.maps[idx], room_reg, 2);
+                self.gen_wfc_pinned(
+                    idx,
+                    Some(room_reg),
+                    "resources/wfc_6x6.xp",
+                    9,
+                    9,
+                    2,
+                    false,
+                    Symmetry::Mirror,
+                    &pins,
+                );
             }
         }
 
@@ -301,9 +659,15 @@ impl MapGenerator {
         self.gen_bsp_ruin(idx, Some(region_bottom));
 
         let all_regions = get_all_regions(&self.maps[idx], &self.maps[idx].get_region());
-        connect_regions(&mut self.maps[idx], all_regions, TileType::Floor, false);
+        connect_regions(
+            &mut self.maps[idx],
+            all_regions,
+            TileType::Floor,
+            false,
+            &mut self.rng,
+        );
 
-        add_vegetation(&mut self.maps[idx], region_top, false);
+        add_vegetation(&mut self.maps[idx], region_top, false, &mut self.rng);
     }
 
     pub fn wfc_01(&mut self, idx: usize) {
@@ -319,6 +683,7 @@ impl MapGenerator {
             20,
             10,
             true,
+            Symmetry::Mirror,
         );
         if self.rng.range(0, 2) < 1 {
             self.gen_digger(idx, Some(region_middle));
@@ -330,7 +695,8 @@ impl MapGenerator {
             if self.rng.range(0, 4) > 0 {
                 let room_reg =
                     &CustomRegion::new_rect(room.x1, room.y1, room.width(), room.height());
-                self.gen_wfc(
+                let pins = doorway_pins(&self.maps[idx], room_reg, 2);
+                self.gen_wfc_pinned(
                     idx,
                     Some(room_reg),
                     "resources/small_6x6.xp",
@@ -338,6 +704,8 @@ impl MapGenerator {
                     6,
                     2,
                     false,
+                    Symmetry::Mirror,
+                    &pins,
                 );
             }
         }
@@ -350,18 +718,52 @@ impl MapGenerator {
 
         self.gen_forest(idx, Some(region_top_left));
         let all_regions = get_all_regions(&self.maps[idx], &self.maps[idx].get_region());
-        connect_regions(&mut self.maps[idx], all_regions, TileType::Floor, false);
+        connect_regions(
+            &mut self.maps[idx],
+            all_regions,
+            TileType::Floor,
+            false,
+            &mut self.rng,
+        );
     }
 
     pub fn gen_wfc(
         &mut self,
         idx: usize,
         region: Option<&CustomRegion>,
-        template: &'static str,
+        template: &str,
+        w: i32,
+        h: i32,
+        tile_size: i32,
+        mix_match: bool,
+        symmetry: Symmetry,
+    ) {
+        self.gen_wfc_pinned(
+            idx,
+            region,
+            template,
+            w,
+            h,
+            tile_size,
+            mix_match,
+            symmetry,
+            &[],
+        );
+    }
+
+    /// Same as gen_wfc, but the output must match the pinned map tiles (see doorway_pins and
+    /// edge_pins).
+    pub fn gen_wfc_pinned(
+        &mut self,
+        idx: usize,
+        region: Option<&CustomRegion>,
+        template: &str,
         w: i32,
         h: i32,
         tile_size: i32,
         mix_match: bool,
+        symmetry: Symmetry,
+        pins: &[(Position, Pin)],
     ) {
         let map_region = &self.maps[idx].get_region();
         let reg = if region != None {
@@ -377,9 +779,28 @@ impl MapGenerator {
         //input.generate(&mut self.maps[idx]);
         //input.repeat_template_cont(&mut self.maps[idx]);
         //input.repeat_template(&mut self.maps[idx]);
-        let mut wfc = WaveFunctionCollapse::new(tile_size, &reg, mix_match);
+        let mut wfc = WaveFunctionCollapse::new(tile_size, &reg, mix_match, symmetry);
+        for (pos, pin) in pins.iter() {
+            wfc.pin(*pos, *pin);
+        }
         // (output, input taken, template width, template height, rng)
         wfc.generate(&mut self.maps[idx], &self.wfc_input, w, h, &mut self.rng);
+
+        let stats = wfc.stats;
+        if stats.contradictions > 0 {
+            println!(
+                "WFC {}: {} contradictions, {} backtracks, {} retries{}",
+                template,
+                stats.contradictions,
+                stats.backtracks,
+                stats.retries,
+                if stats.failures > 0 { ", failed" } else { "" }
+            );
+        }
+        self.wfc_stats
+            .entry(template.to_string())
+            .or_default()
+            .add(&stats);
     }
 
     pub fn gen_forest(&mut self, idx: usize, region: Option<&CustomRegion>) {
@@ -390,18 +811,30 @@ impl MapGenerator {
             map_region
         };
 
-        make_chaotic(&mut self.maps[idx], reg, 50);
+        make_chaotic(&mut self.maps[idx], reg, 50, &mut self.rng);
 
         // n_iterations, n_walls_rule, min_cave_size, open_halls, dry_caves
         let mut cell_automata = CellularAutomata::new(reg, 12, 5, 20, true, true);
-        cell_automata.generate(&mut self.maps[idx]);
+        cell_automata.generate(&mut self.maps[idx], &mut self.rng);
 
         // Make two big lakes.
-        make_lake(&mut self.maps[idx], reg, TileType::ShallowWater, 600);
-        make_lake(&mut self.maps[idx], reg, TileType::ShallowWater, 600);
+        make_lake(
+            &mut self.maps[idx],
+            reg,
+            TileType::ShallowWater,
+            600,
+            &mut self.rng,
+        );
+        make_lake(
+            &mut self.maps[idx],
+            reg,
+            TileType::ShallowWater,
+            600,
+            &mut self.rng,
+        );
 
         let mut cell_automata2 = CellularAutomata::new(reg, 1, 3, 20, true, true);
-        cell_automata2.generate(&mut self.maps[idx]);
+        cell_automata2.generate(&mut self.maps[idx], &mut self.rng);
 
         self.regions.insert(
             get_all_regions(&self.maps[idx], &reg)
@@ -412,7 +845,7 @@ impl MapGenerator {
         );
 
         apply_forest_theme(&mut self.maps[idx], reg);
-        add_vegetation(&mut self.maps[idx], reg, true);
+        add_vegetation(&mut self.maps[idx], reg, true, &mut self.rng);
     }
 
     pub fn gen_cave(&mut self, idx: usize, region: Option<&CustomRegion>) {
@@ -432,11 +865,17 @@ impl MapGenerator {
 
         // n_iterations, n_walls_rule, min_cave_size, open_halls, dry_caves
         let mut cell_automata = CellularAutomata::new(reg, 12, 5, 20, false, false);
-        cell_automata.generate(&mut self.maps[idx]);
-        make_lake(&mut self.maps[idx], reg, TileType::ShallowWater, 200);
+        cell_automata.generate(&mut self.maps[idx], &mut self.rng);
+        make_lake(
+            &mut self.maps[idx],
+            reg,
+            TileType::ShallowWater,
+            200,
+            &mut self.rng,
+        );
 
         let mut cell_automata2 = CellularAutomata::new(reg, 1, 4, 5, true, true);
-        cell_automata2.generate(&mut self.maps[idx]);
+        cell_automata2.generate(&mut self.maps[idx], &mut self.rng);
 
         self.regions.insert(
             get_all_regions(&self.maps[idx], &reg)
@@ -447,7 +886,7 @@ impl MapGenerator {
         );
 
         if self.rng.range(0, 3) < 1 {
-            add_vegetation(&mut self.maps[idx], reg, false);
+            add_vegetation(&mut self.maps[idx], reg, false, &mut self.rng);
         }
     }
 
@@ -468,13 +907,13 @@ impl MapGenerator {
 
         // n_iterations, n_walls_rule, min_cave_size, open_halls, dry_caves
         let mut cell_automata = CellularAutomata::new(reg, 12, 5, 5, false, true);
-        cell_automata.generate(&mut self.maps[idx]);
+        cell_automata.generate(&mut self.maps[idx], &mut self.rng);
 
         chance = self.rng.range(0, 3);
         let rule = if chance <= 1 { 5 } else { 2 };
 
         let mut cell_automata2 = CellularAutomata::new(reg, 5, rule, 5, true, true);
-        cell_automata2.generate(&mut self.maps[idx]);
+        cell_automata2.generate(&mut self.maps[idx], &mut self.rng);
 
         self.regions.insert(
             get_all_regions(&self.maps[idx], &reg)
@@ -485,7 +924,7 @@ impl MapGenerator {
         );
 
         if self.rng.range(0, 5) < 1 {
-            add_vegetation(&mut self.maps[idx], reg, false);
+            add_vegetation(&mut self.maps[idx], reg, false, &mut self.rng);
         }
     }
 
@@ -546,10 +985,16 @@ impl MapGenerator {
             map_region
         };
 
-        make_lake(&mut self.maps[idx], reg, TileType::ShallowWater, 100);
+        make_lake(
+            &mut self.maps[idx],
+            reg,
+            TileType::ShallowWater,
+            100,
+            &mut self.rng,
+        );
         let mut cell_automata = CellularAutomata::new(reg, 2, 3, 10, true, false);
-        cell_automata.generate(&mut self.maps[idx]);
-        add_vegetation(&mut self.maps[idx], reg, false);
+        cell_automata.generate(&mut self.maps[idx], &mut self.rng);
+        add_vegetation(&mut self.maps[idx], reg, false, &mut self.rng);
     }
 
     pub fn gen_bsp_ruin_2(&mut self, idx: usize, region: Option<&CustomRegion>) {
@@ -563,10 +1008,16 @@ impl MapGenerator {
             map_region
         };
 
-        make_lake(&mut self.maps[idx], reg, TileType::ShallowWater, 100);
+        make_lake(
+            &mut self.maps[idx],
+            reg,
+            TileType::ShallowWater,
+            100,
+            &mut self.rng,
+        );
         //let mut cell_automata = CellularAutomata::new(1, 1, 5, true, false);
         //cell_automata.generate(&mut self.map);
-        add_vegetation(&mut self.maps[idx], reg, false);
+        add_vegetation(&mut self.maps[idx], reg, false, &mut self.rng);
     }
 
     pub fn gen_digger(&mut self, idx: usize, region: Option<&CustomRegion>) {
@@ -603,13 +1054,13 @@ impl MapGenerator {
 
         // n_iterations, n_walls_rule, min_cave_size, open_halls, dry_caves
         let mut cell_automata = CellularAutomata::new(reg, 3, 7, 10, false, false);
-        cell_automata.generate(&mut self.maps[idx]);
+        cell_automata.generate(&mut self.maps[idx], &mut self.rng);
         if self.rng.range(0, 2) < 1 {
-            add_vegetation(&mut self.maps[idx], reg, false);
+            add_vegetation(&mut self.maps[idx], reg, false, &mut self.rng);
         }
     }
 
-    pub fn gen_prefab_map(&mut self, idx: usize, template: &'static str) {
+    pub fn gen_prefab_map(&mut self, idx: usize, template: &str) {
         let mut map = PrefabMap::new(template);
         map.generate(&mut self.maps[idx]);
     }
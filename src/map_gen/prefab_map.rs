@@ -11,18 +11,20 @@ use bracket_lib::prelude::{to_char, XpFile};
  */
 
 pub struct PrefabMap {
-    template: &'static str,
+    template: String,
 }
 
 #[allow(dead_code)]
 impl PrefabMap {
-    pub fn new(template: &'static str) -> Self {
-        Self { template }
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+        }
     }
 
     pub fn generate(&mut self, map: &mut Map) {
         map.tiles = vec![Tile::floor(); (map.width * map.height) as usize];
-        let prefab_map = XpFile::from_resource(self.template).unwrap();
+        let prefab_map = XpFile::from_resource(&self.template).unwrap();
 
         for layer in &prefab_map.layers {
             println!("height: {}", layer.height);
@@ -41,7 +43,7 @@ impl PrefabMap {
     }
 
     pub fn repeat_template(&mut self, map: &mut Map) {
-        let prefab_map = XpFile::from_resource(self.template).unwrap();
+        let prefab_map = XpFile::from_resource(&self.template).unwrap();
 
         for layer in &prefab_map.layers {
             let tx = map.width / (layer.width as i32);
@@ -66,7 +68,7 @@ impl PrefabMap {
     }
 
     pub fn repeat_template_cont(&mut self, map: &mut Map) {
-        let prefab_map = XpFile::from_resource(self.template).unwrap();
+        let prefab_map = XpFile::from_resource(&self.template).unwrap();
 
         for layer in &prefab_map.layers {
             let xt = layer.width as i32 - 1;
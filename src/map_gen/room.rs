@@ -25,7 +25,7 @@ use bracket_lib::prelude::{Point, RandomNumberGenerator, Rect};
  */
 
 pub trait Operations {
-    fn get_wall(&self, map: &Map, dir: Direction) -> Point;
+    fn get_wall(&self, map: &Map, dir: Direction, rng: &mut RandomNumberGenerator) -> Point;
     fn get_borders(&self, map: &Map) -> Vec<Point>;
     fn get_area_idx(&self, map: &Map) -> Vec<usize>;
 }
@@ -33,9 +33,8 @@ pub trait Operations {
 pub type Room = Rect;
 
 impl Operations for Room {
-    fn get_wall(&self, map: &Map, dir: Direction) -> Point {
+    fn get_wall(&self, map: &Map, dir: Direction, rng: &mut RandomNumberGenerator) -> Point {
         let borders = self.get_borders(map);
-        let mut rng = RandomNumberGenerator::new();
 
         for pt in borders {
             match dir {
@@ -1,5 +1,6 @@
 use crate::utils::colors::*;
 use bracket_lib::prelude::{to_cp437, ColorPair};
+use serde::{Deserialize, Serialize};
 
 /*
  *
@@ -9,10 +10,11 @@ use bracket_lib::prelude::{to_cp437, ColorPair};
  *
  */
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize, Deserialize)]
 pub enum TileType {
     Empty,
     Exit,
+    ExitUp,
     Wall,
     InvisibleWall,
     Floor,
@@ -33,6 +35,7 @@ pub enum TileType {
     DeepWater,
     Computer,
     FakeMob,
+    Ashes,
 }
 
 impl Default for TileType {
@@ -41,7 +44,7 @@ impl Default for TileType {
     }
 }
 
-#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[derive(Copy, Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Tile {
     pub ttype: TileType,
     pub block: bool,
@@ -76,6 +79,16 @@ impl Tile {
         }
     }
 
+    pub fn exit_up() -> Self {
+        Self {
+            ttype: TileType::ExitUp,
+            block: false,
+            glyph: to_cp437('<'),
+            color: ColorPair::new(color("BrightMagenta", 1.0), color("Background", 1.0)),
+            ..Default::default()
+        }
+    }
+
     pub fn wall() -> Self {
         Self {
             ttype: TileType::Wall,
@@ -243,6 +256,15 @@ impl Tile {
         }
     }
 
+    pub fn ashes() -> Self {
+        Self {
+            ttype: TileType::Ashes,
+            glyph: to_cp437('░'),
+            color: ColorPair::new(color("BrightBlack", 1.0), color("Background", 1.0)),
+            ..Default::default()
+        }
+    }
+
     pub fn computer() -> Self {
         Self {
             ttype: TileType::Computer,
@@ -321,13 +343,16 @@ impl Tile {
             TileType::Mushroom => {
                 self.color = ColorPair::new(color("BrightRed", 1.0), color("Background", 1.0));
             }
+            TileType::Ashes => {
+                self.color = ColorPair::new(color("BrightBlack", 1.0), color("Background", 1.0));
+            }
             TileType::Computer => {
                 self.color = ColorPair::new(color("Magenta", 1.0), color("Background", 1.0));
             }
             TileType::FakeMob => {
                 self.color = ColorPair::new(color("Red", 1.0), color("Background", 1.0));
             }
-            TileType::Exit => {
+            TileType::Exit | TileType::ExitUp => {
                 self.color = ColorPair::new(color("BrightMagenta", 1.0), color("Background", 1.0));
             }
             _ => {
@@ -360,9 +385,11 @@ pub fn get_tile_function(ttype: TileType) -> Tile {
         TileType::TallGrass => Tile::tallgrass(),
         TileType::Flower => Tile::flower(),
         TileType::Mushroom => Tile::mushroom(),
+        TileType::Ashes => Tile::ashes(),
         TileType::Computer => Tile::computer(),
         TileType::FakeMob => Tile::fakemob(),
         TileType::Exit => Tile::exit(),
+        TileType::ExitUp => Tile::exit_up(),
         _ => Tile::floor(),
     }
 }
@@ -0,0 +1,91 @@
+use super::{common::get_region, Map, Position};
+use std::fmt;
+
+/*
+ *
+ * validation.rs
+ * -------------
+ * Checks that a generated level can be played: the spawn point is free and every exit can be
+ * reached from it.
+ *
+ */
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MapError {
+    SpawnOutOfBounds(Position),
+    SpawnBlocked(Position),
+    NoExit,
+    UnreachableExit(Position),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::SpawnOutOfBounds(p) => write!(f, "spawn ({}, {}) out of bounds", p.x, p.y),
+            MapError::SpawnBlocked(p) => write!(f, "spawn ({}, {}) on a blocking tile", p.x, p.y),
+            MapError::NoExit => write!(f, "no exit to the next level"),
+            MapError::UnreachableExit(p) => write!(f, "exit ({}, {}) unreachable", p.x, p.y),
+        }
+    }
+}
+
+/// Checks that the spawn point is inside the map and not on a blocking tile.
+pub fn validate_spawn(map: &Map) -> Result<(), MapError> {
+    let spawn = map.spawn_point;
+    if !map.in_map_bounds(spawn) {
+        return Err(MapError::SpawnOutOfBounds(spawn));
+    }
+    if map.tiles[map.idx(spawn.x, spawn.y)].block {
+        return Err(MapError::SpawnBlocked(spawn));
+    }
+
+    Ok(())
+}
+
+/// Checks the spawn point, and that the level has a ">" and every exit ("<" and ">") is
+/// reachable from the spawn.
+pub fn validate_map(map: &Map) -> Result<(), MapError> {
+    validate_spawn(map)?;
+
+    let reached = reachable_tiles(map);
+    let exits: Vec<usize> = (0..map.tiles.len())
+        .filter(|i| map.is_exit(*i) || map.is_exit_up(*i))
+        .collect();
+    if !exits.iter().any(|i| map.is_exit(*i)) {
+        return Err(MapError::NoExit);
+    }
+    for i in exits {
+        if !is_reachable(map, &reached, i) {
+            return Err(MapError::UnreachableExit(map.idx_pos(i)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Flood fills the map from the spawn point, marking every tile that can be walked to.
+pub fn reachable_tiles(map: &Map) -> Vec<bool> {
+    let mut reached = vec![false; map.tiles.len()];
+    if !map.in_map_bounds(map.spawn_point) {
+        return reached;
+    }
+
+    let spawn_idx = map.idx(map.spawn_point.x, map.spawn_point.y);
+    for i in get_region(spawn_idx, map) {
+        reached[i] = true;
+    }
+
+    reached
+}
+
+/// Exits aren't walkable tiles themselves, so they're reachable when one of their neighbors is.
+pub fn is_reachable(map: &Map, reached: &[bool], idx: usize) -> bool {
+    if reached[idx] {
+        return true;
+    }
+
+    let pt = map.idx_pos(idx);
+    [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+        map.in_map_bounds_xy(pt.x + dx, pt.y + dy) && reached[map.idx(pt.x + dx, pt.y + dy)]
+    })
+}
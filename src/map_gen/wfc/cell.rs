@@ -90,6 +90,22 @@ impl Cell {
         self.sum_possible_weights_log -= f * f.log2();
     }
 
+    /// Puts back a removed tile (when backtracking), keeping the possible tiles in order.
+    /// The sums of possible weights are left as they are: adding the weight back would let them
+    /// drift from the real totals after many undos, so recompute them with
+    /// `total_possible_tile_freq` once done restoring.
+    pub fn restore_tile(&mut self, tile_idx: usize) {
+        if self.possible[tile_idx] {
+            return;
+        }
+        self.possible[tile_idx] = true;
+        let pos = self
+            .possible_tiles
+            .binary_search(&tile_idx)
+            .unwrap_or_else(|pos| pos);
+        self.possible_tiles.insert(pos, tile_idx);
+    }
+
     /// Adds up the relative frequencies of all possible tiles.
     /// Also calculates the log sum.
     pub fn total_possible_tile_freq(&mut self, freq: &HashMap<usize, f32>) {
@@ -109,7 +125,8 @@ impl Cell {
         //println!("noise: {}", self.entropy_noise);
     }
 
-    /// Selects a tile based on the frequency table.
+    /// Selects a tile based on the frequency table. Rounding may leave a bit of the weight
+    /// unaccounted for, in which case the last possible tile is picked.
     pub fn choose_tile(
         &self,
         freq: &HashMap<usize, f32>,
@@ -126,7 +143,10 @@ impl Cell {
             }
         }
 
-        unreachable!("sum_possible_weights was inconsistent!");
+        *self
+            .possible_tiles
+            .last()
+            .expect("choose_tile on a cell with no possible tiles")
     }
 
     /// Checks if there's a contradiction in the current cell.
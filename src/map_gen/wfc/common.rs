@@ -1,5 +1,5 @@
 use super::TileType;
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 pub fn tile_idx(tile_size: i32, x: i32, y: i32) -> usize {
     ((y * tile_size) + x) as usize
@@ -10,8 +10,22 @@ pub fn in_tile_bounds(tile_size: i32, x: i32, y: i32) -> bool {
     x >= 0 && x < tile_size && y >= 0 && y < tile_size
 }
 
-// https://www.reddit.com/r/rust/comments/38zzbk/best_way_to_remove_duplicates_from_a_veclist/
-pub fn deduplicate(vs: &mut Vec<Vec<TileType>>) {
-    let set: HashSet<Vec<TileType>> = vs.drain(..).collect();
-    vs.extend(set.into_iter());
+/// Removes repeated patterns, keeping the first occurrence of each in its original order (the
+/// order must not depend on hashing, otherwise the same seed would produce different maps).
+/// Returns how many times each of the remaining patterns was found.
+pub fn deduplicate(vs: &mut Vec<Vec<TileType>>) -> Vec<usize> {
+    let mut seen: HashMap<Vec<TileType>, usize> = HashMap::new();
+    let mut counts: Vec<usize> = Vec::new();
+    vs.retain(|v| match seen.get(v) {
+        Some(i) => {
+            counts[*i] += 1;
+            false
+        }
+        None => {
+            seen.insert(v.clone(), counts.len());
+            counts.push(1);
+            true
+        }
+    });
+    counts
 }
@@ -1,6 +1,7 @@
-use super::{CustomRegion, Map, Point, TileType};
+use super::{get_tile_function, CustomRegion, Map, Point, TileType};
 use crate::utils::directions::*;
 use bracket_lib::prelude::RandomNumberGenerator;
+use serde::Deserialize;
 use std::collections::HashMap;
 
 mod common;
@@ -27,6 +28,113 @@ use wave::*;
  *
  */
 
+/// Which orientations of the input are taken as patterns, so the samples don't have to be drawn
+/// in every orientation.
+#[derive(Debug, PartialEq, Copy, Clone, Default, Deserialize)]
+pub enum Symmetry {
+    None, // Only the patterns as drawn.
+    #[default]
+    Mirror, // Plus their horizontal and vertical mirrors (and both, the 180 degrees rotation).
+    All,  // Plus the 90 and 270 degrees rotations.
+}
+
+// Times the whole wave is started over when backtracking can't solve a contradiction.
+const MAX_RETRIES: usize = 3;
+
+/// How hard it was to solve a wave; added up per template by the MapGenerator.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WfcStats {
+    pub runs: usize,
+    pub contradictions: usize,
+    pub backtracks: usize,
+    pub retries: usize,
+    pub failures: usize, // Runs that gave up, leaving the region as it was.
+}
+
+impl WfcStats {
+    pub fn add(&mut self, other: &WfcStats) {
+        self.runs += other.runs;
+        self.contradictions += other.contradictions;
+        self.backtracks += other.backtracks;
+        self.retries += other.retries;
+        self.failures += other.failures;
+    }
+}
+
+/// What a map tile of the output must be. Pinned tiles are settled before the wave starts.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Pin {
+    Tile(TileType), // Exactly this tile.
+    Open,           // Any tile that doesn't block.
+    Closed,         // Any tile that blocks.
+}
+
+impl Pin {
+    fn allows(&self, ttype: TileType) -> bool {
+        match self {
+            Pin::Tile(t) => *t == ttype,
+            Pin::Open => !get_tile_function(ttype).block,
+            Pin::Closed => get_tile_function(ttype).block,
+        }
+    }
+}
+
+/// Part of the region actually painted by the WFC: a whole number of tiles.
+fn painted_area(region: &CustomRegion, tile_size: i32) -> (i32, i32, i32, i32) {
+    let x2 = region.x1 + (region.width / tile_size) * tile_size;
+    let y2 = region.y1 + (region.height / tile_size) * tile_size;
+    (region.x1, region.y1, x2, y2)
+}
+
+/// Pins the whole edge of the painted area to `edge`, except the `openings`, pinned to `opening`.
+pub fn edge_pins(
+    region: &CustomRegion,
+    tile_size: i32,
+    edge: Pin,
+    openings: &[Point],
+    opening: Pin,
+) -> Vec<(Point, Pin)> {
+    let (x1, y1, x2, y2) = painted_area(region, tile_size);
+    let mut pins = Vec::new();
+    for y in y1..y2 {
+        for x in x1..x2 {
+            if x == x1 || x == x2 - 1 || y == y1 || y == y2 - 1 {
+                let pt = Point::new(x, y);
+                let pin = if openings.contains(&pt) {
+                    opening
+                } else {
+                    edge
+                };
+                pins.push((pt, pin));
+            }
+        }
+    }
+    pins
+}
+
+/// Pins as Open the tiles of the edge of the painted area that something walkable (a tunnel, a
+/// doorway) touches from outside, so the output doesn't wall them off.
+pub fn doorway_pins(map: &Map, region: &CustomRegion, tile_size: i32) -> Vec<(Point, Pin)> {
+    let (x1, y1, x2, y2) = painted_area(region, tile_size);
+    let inside = |x: i32, y: i32| x >= x1 && x < x2 && y >= y1 && y < y2;
+    let mut pins = Vec::new();
+    for y in y1..y2 {
+        for x in x1..x2 {
+            let touched = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+                let (nx, ny) = (x + dx, y + dy);
+                !inside(nx, ny) && map.in_map_bounds_xy(nx, ny) && {
+                    let idx = map.idx(nx, ny);
+                    map.is_walkable(idx) || map.is_door(idx)
+                }
+            });
+            if touched {
+                pins.push((Point::new(x, y), Pin::Open));
+            }
+        }
+    }
+    pins
+}
+
 #[derive(Debug, Clone)]
 pub struct WaveFunctionCollapse<'a> {
     tile_size: i32,
@@ -35,11 +143,19 @@ pub struct WaveFunctionCollapse<'a> {
     frequencies: HashMap<usize, f32>,
     region: &'a CustomRegion,
     mix_match: bool, // Used for larger inputs.
+    symmetry: Symmetry,
+    pins: Vec<(Point, Pin)>,
+    pub stats: WfcStats,
 }
 
 #[allow(dead_code)]
 impl<'a> WaveFunctionCollapse<'a> {
-    pub fn new(tile_size: i32, region: &'a CustomRegion, mix_match: bool) -> Self {
+    pub fn new(
+        tile_size: i32,
+        region: &'a CustomRegion,
+        mix_match: bool,
+        symmetry: Symmetry,
+    ) -> Self {
         Self {
             tile_size,
             patterns: Vec::new(),
@@ -47,9 +163,17 @@ impl<'a> WaveFunctionCollapse<'a> {
             frequencies: HashMap::new(),
             region,
             mix_match,
+            symmetry,
+            pins: Vec::new(),
+            stats: WfcStats::default(),
         }
     }
 
+    /// The map tile at `pos` must match `pin`.
+    pub fn pin(&mut self, pos: Point, pin: Pin) {
+        self.pins.push((pos, pin));
+    }
+
     /// Runs the whole WFC algorithm.
     pub fn generate(
         &mut self,
@@ -60,37 +184,47 @@ impl<'a> WaveFunctionCollapse<'a> {
         rng: &mut RandomNumberGenerator,
     ) -> bool {
         self.build_patterns(input_map, input_x, input_y);
-        let patterns = self.patterns.clone();
-        //println!("Patterns: {}", patterns.len());
+        //println!("Patterns: {}", self.patterns.len());
         //map.tiles = vec![Tile::woodenfloor(); (map.width * map.height) as usize];
 
-        deduplicate(&mut self.patterns);
+        // Orientations that look the same become a single, more frequent, pattern.
+        let counts = deduplicate(&mut self.patterns);
 
         self.build_constraints(); // patterns + adjacency rules
         let constraints = self.constraints.clone();
-        self.compute_frequencies(&patterns, &constraints); // frequency hints
+        self.compute_frequencies(&counts); // frequency hints
 
         let out_width = self.region.width / self.tile_size;
         let out_height = self.region.height / self.tile_size;
         let out_size = out_width * out_height;
 
-        // Initialize Cells.
-        let cells = self.init_cells(out_size, rng);
+        self.stats.runs += 1;
+        for retry in 0..=MAX_RETRIES {
+            if retry > 0 {
+                self.stats.retries += 1;
+            }
 
-        // Initialize Wave.
-        let mut wave = Wave::new(cells, constraints, out_width, out_height);
-        wave.init_entropy_queue();
+            // Initialize Cells.
+            let cells = self.init_cells(out_size, rng);
 
-        // Run Wave.
-        if !self.run_wave(&mut wave, rng) {
-            return false;
-        }
+            // Initialize Wave.
+            let mut wave = Wave::new(cells, constraints.clone(), out_width, out_height);
+            wave.init_entropy_queue();
 
-        // Generate the output.
-        //map.tiles = vec![Tile::wall(); (output_map.width * output_map.height) as usize];
-        self.generate_output(wave, output_map);
+            // Run Wave, once the pinned tiles are settled.
+            let solved = self.apply_pins(&mut wave) && self.run_wave(&mut wave, rng);
+            self.stats.contradictions += wave.stats.contradictions;
+            self.stats.backtracks += wave.stats.backtracks;
+            if solved {
+                // Generate the output.
+                //map.tiles = vec![Tile::wall(); (output_map.width * output_map.height) as usize];
+                self.generate_output(wave, output_map);
+                return true;
+            }
+        }
 
-        true
+        self.stats.failures += 1;
+        false
     }
 
     /// Initialize all the cells.
@@ -108,15 +242,44 @@ impl<'a> WaveFunctionCollapse<'a> {
         cells
     }
 
-    /// Runs the core WFC solver.
+    /// Removes from the cells every pattern that doesn't match the pinned tiles over them, and
+    /// propagates that. Returns false if the pins contradict each other.
+    fn apply_pins(&self, wave: &mut Wave) -> bool {
+        if self.pins.is_empty() {
+            return true;
+        }
+
+        let (x1, y1, x2, y2) = painted_area(self.region, self.tile_size);
+        for (pos, pin) in self.pins.iter() {
+            if pos.x < x1 || pos.x >= x2 || pos.y < y1 || pos.y >= y2 {
+                continue;
+            }
+            let (dx, dy) = (pos.x - x1, pos.y - y1);
+            let cell = Point::new(dx / self.tile_size, dy / self.tile_size);
+            let j = tile_idx(self.tile_size, dx % self.tile_size, dy % self.tile_size);
+
+            let banned: Vec<usize> = self
+                .constraints
+                .iter()
+                .filter(|t| !pin.allows(t.pattern[j]))
+                .map(|t| t.idx)
+                .collect();
+            if !wave.ban_tiles(cell, &banned, &self.frequencies) {
+                return false;
+            }
+        }
+
+        wave.propagate(&self.frequencies)
+    }
+
+    /// Runs the core WFC solver, backtracking on contradictions.
     fn run_wave(&mut self, wave: &mut Wave, rng: &mut RandomNumberGenerator) -> bool {
         while wave.uncollapsed_cells > 0 {
             let next_coord = wave.choose_next_cell();
             wave.collapse_cell_at(next_coord, &self.frequencies, rng);
-            if !wave.propagate(&self.frequencies) {
+            if !wave.propagate(&self.frequencies) && !wave.backtrack(&self.frequencies) {
                 return false;
             }
-            wave.uncollapsed_cells -= 1;
         }
         true
     }
@@ -148,6 +311,18 @@ impl<'a> WaveFunctionCollapse<'a> {
     /// Builds tiles of size tile_size*tile_size from the given map cells.
     fn build_patterns(&mut self, map: &Map, input_x: i32, input_y: i32) {
         self.patterns.clear();
+        let orientations: &[&str] = match self.symmetry {
+            Symmetry::None => &["normal"],
+            Symmetry::Mirror => &["normal", "vertical", "horizontal", "both"],
+            Symmetry::All => &[
+                "normal",
+                "vertical",
+                "horizontal",
+                "both",
+                "rot90",
+                "rot270",
+            ],
+        };
         // Navigate the coordinates of each tile.
         let y1 = if !self.mix_match {
             input_y
@@ -172,63 +347,46 @@ impl<'a> WaveFunctionCollapse<'a> {
                     Point::new((tx + 1) * self.tile_size, (ty + 1) * self.tile_size)
                 };
                 //println!("Start: {:?}, End: {:?}", start, end);
-                let normal_pattern = self.get_pattern(map, start, end, "normal");
-                let vert_pattern = self.get_pattern(map, start, end, "vertical");
-                let horiz_pattern = self.get_pattern(map, start, end, "horizontal");
-                let verthoriz_pattern = self.get_pattern(map, start, end, "both");
+                for rot in orientations.iter() {
+                    let pattern = self.get_pattern(map, start, end, rot);
+                    self.patterns.push(pattern);
+                }
                 //let inverted_pattern = self.get_pattern(map, start, end, "invert");
-                self.patterns.push(normal_pattern);
-                self.patterns.push(vert_pattern);
-                self.patterns.push(horiz_pattern);
-                self.patterns.push(verthoriz_pattern);
                 //self.patterns.push(inverted_pattern);
             }
         }
     }
 
-    /// Returns a pattern (reflected or not) taken from the input.
+    /// Returns a pattern (reflected, rotated or not) taken from the input.
     fn get_pattern(&mut self, map: &Map, start: Point, end: Point, rot: &str) -> Vec<TileType> {
         let mut pattern: Vec<TileType> = Vec::new();
-        for y in start.y..end.y {
-            for x in start.x..end.x {
-                let idx;
-                match rot {
-                    "vertical" => {
-                        idx = map.idx(x, end.y - (y + 1));
-                    }
-                    "horizontal" => {
-                        idx = map.idx(end.x - (x + 1), y);
-                    }
-                    "both" => {
-                        idx = map.idx(end.x - (x + 1), end.y - (y + 1));
-                    }
-                    "invert" => {
-                        if map.in_map_bounds_xy(y, x) {
-                            idx = map.idx(y, x);
-                        } else {
-                            idx = map.idx(x, y);
-                        }
-                        //idx = map.idx(end.y - (y + 1), end.x - (x + 1));
-                    }
-                    _ => {
-                        idx = map.idx(x, y);
-                    }
-                }
+        // Patterns are square, so rotating them keeps their size.
+        let last = end.x - start.x - 1;
+        for y in 0..end.y - start.y {
+            for x in 0..end.x - start.x {
+                // Position in the input pattern that goes to (x, y) in the new one.
+                let (px, py) = match rot {
+                    "vertical" => (x, last - y),
+                    "horizontal" => (last - x, y),
+                    "both" => (last - x, last - y),
+                    "rot90" => (y, last - x),
+                    "rot270" => (last - y, x),
+                    "invert" => (y, x),
+                    _ => (x, y),
+                };
+                let idx = map.idx(start.x + px, start.y + py);
                 pattern.push(map.tiles[idx].ttype);
             }
         }
         pattern
     }
 
-    /// Compute the relative frequencies of each tile.
-    fn compute_frequencies(&mut self, patterns: &Vec<Vec<TileType>>, constraints: &Vec<MapTile>) {
-        // Calculate absolute frequencies.
-        for tile in constraints.iter() {
-            for p in patterns.iter() {
-                if tile.pattern == *p {
-                    *self.frequencies.entry(tile.idx).or_insert(0.0) += 1.0;
-                }
-            }
+    /// Compute the relative frequencies of each tile, given how many times each one was found
+    /// in the input.
+    fn compute_frequencies(&mut self, counts: &[usize]) {
+        // Absolute frequencies.
+        for tile in self.constraints.iter() {
+            *self.frequencies.entry(tile.idx).or_insert(0.0) += counts[tile.idx] as f32;
         }
         // Update absolute frequencies to relative frequencies.
         let total: f32 = self.frequencies.values().sum();
@@ -1,8 +1,13 @@
-use super::{Cell, MapTile, Point};
+use super::{Cell, MapTile, Point, WfcStats};
 use crate::utils::directions::*;
 use bracket_lib::prelude::RandomNumberGenerator;
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+// How many of the last collapses can be undone after a contradiction.
+const BACKTRACK_DEPTH: usize = 8;
+// Backtracks allowed in a single run, before giving up on it.
+const MAX_BACKTRACKS: usize = 64;
 
 #[derive(Debug, Clone)]
 pub struct Wave {
@@ -11,6 +16,8 @@ pub struct Wave {
     maptiles: Vec<MapTile>,
     entropy_queue: BinaryHeap<CoordEntropy>,
     tile_removals: Vec<RemovalUpdate>, // stack
+    history: VecDeque<Collapse>,       // Last collapses, with everything they changed.
+    pub stats: WfcStats,
     pub out_width: i32,
     pub out_height: i32,
 }
@@ -25,6 +32,8 @@ impl Wave {
             maptiles,
             entropy_queue: BinaryHeap::new(),
             tile_removals: Vec::new(),
+            history: VecDeque::new(),
+            stats: WfcStats::default(),
             out_height,
             out_width,
         }
@@ -74,6 +83,48 @@ impl Wave {
         unreachable!("entropy_queue is empty!");
     }
 
+    /// Pushes the current entropy of the cell at a given point to the entropy queue.
+    fn push_entropy(&mut self, pt: Point) {
+        let cell = &self.cells[self.cell_at(pt.x, pt.y)];
+        self.entropy_queue.push(CoordEntropy {
+            entropy: Entropy {
+                entropy: cell.entropy(),
+                noise: cell.entropy_noise,
+            },
+            coord: pt,
+        });
+    }
+
+    /// Removes a tile from the cell at a given point, to be propagated later.
+    fn remove_tile_at(&mut self, pt: Point, tile: usize, freq: &HashMap<usize, f32>) {
+        let idx = self.cell_at(pt.x, pt.y);
+        self.cells[idx].remove_tile(tile, freq);
+
+        let removal = RemovalUpdate { tile, coord: pt };
+        if let Some(collapse) = self.history.back_mut() {
+            collapse.changes.push(Change::Removal(removal.clone()));
+        }
+        self.tile_removals.push(removal);
+    }
+
+    /// Removes the given tiles from the cell at a point, before any collapse (these removals
+    /// can't be undone). Returns false if that leaves the cell with nothing.
+    pub fn ban_tiles(&mut self, pt: Point, tiles: &[usize], freq: &HashMap<usize, f32>) -> bool {
+        let idx = self.cell_at(pt.x, pt.y);
+        for tile in tiles.iter() {
+            if self.cells[idx].possible[*tile] {
+                self.remove_tile_at(pt, *tile, freq);
+            }
+        }
+
+        if self.cells[idx].contradiction_check() {
+            self.stats.contradictions += 1;
+            return false;
+        }
+        self.push_entropy(pt);
+        true
+    }
+
     /// Collapses a cell at a given point.
     /// That is, remove all the possibilities except the only possible one.
     pub fn collapse_cell_at(
@@ -83,21 +134,92 @@ impl Wave {
         rng: &mut RandomNumberGenerator,
     ) {
         let idx = self.cell_at(pt.x, pt.y);
-        let mut cell = &mut self.cells[idx];
-        let locked_tile = cell.choose_tile(freq, rng);
-
-        cell.collapsed = true;
-
-        let possibles = cell.possible_tiles.clone();
-        for idx in possibles.iter() {
-            if *idx != locked_tile {
-                cell.remove_tile(*idx, freq);
-                self.tile_removals.push(RemovalUpdate {
-                    tile: *idx,
-                    coord: pt,
-                });
+        let locked_tile = self.cells[idx].choose_tile(freq, rng);
+
+        self.cells[idx].collapsed = true;
+        self.uncollapsed_cells -= 1;
+
+        // Older collapses can't be undone anymore.
+        if self.history.len() == BACKTRACK_DEPTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(Collapse {
+            coord: pt,
+            tile: locked_tile,
+            changes: Vec::new(),
+        });
+
+        let possibles = self.cells[idx].possible_tiles.clone();
+        for tile in possibles.iter() {
+            if *tile != locked_tile {
+                self.remove_tile_at(pt, *tile, freq);
+            }
+        }
+    }
+
+    /// Undoes the last collapse and everything propagated from it. Returns the collapsed cell
+    /// and the tile it got, or None if there's nothing to undo.
+    fn undo_collapse(&mut self, freq: &HashMap<usize, f32>) -> Option<(Point, usize)> {
+        let collapse = self.history.pop_back()?;
+
+        let mut restored: Vec<Point> = Vec::new();
+        let mut seen = vec![false; self.cells.len()];
+        for change in collapse.changes.iter().rev() {
+            match change {
+                Change::Removal(removal) => {
+                    let idx = self.cell_at(removal.coord.x, removal.coord.y);
+                    self.cells[idx].restore_tile(removal.tile);
+                    if !seen[idx] {
+                        seen[idx] = true;
+                        restored.push(removal.coord);
+                    }
+                }
+                Change::Enabler(removal, dir) => {
+                    let idx = self.cell_at(removal.coord.x, removal.coord.y);
+                    self.cells[idx].enabler_count[removal.tile].by_direction[*dir] += 1;
+                }
+            }
+        }
+        for pt in restored {
+            let idx = self.cell_at(pt.x, pt.y);
+            self.cells[idx].total_possible_tile_freq(freq);
+            self.push_entropy(pt);
+        }
+
+        let idx = self.cell_at(collapse.coord.x, collapse.coord.y);
+        self.cells[idx].collapsed = false;
+        self.uncollapsed_cells += 1;
+        self.push_entropy(collapse.coord);
+
+        Some((collapse.coord, collapse.tile))
+    }
+
+    /// Recovers from a contradiction: undoes the last collapse and bans the tile it chose from
+    /// that cell. If that's a contradiction too, goes back one more collapse, and so on.
+    /// Returns false when there's nothing left to undo, or after too many backtracks.
+    pub fn backtrack(&mut self, freq: &HashMap<usize, f32>) -> bool {
+        self.tile_removals.clear();
+
+        while self.stats.backtracks < MAX_BACKTRACKS {
+            let (pt, tile) = match self.undo_collapse(freq) {
+                Some(collapse) => collapse,
+                None => return false,
+            };
+            self.stats.backtracks += 1;
+
+            // The ban is part of the previous collapse, so it's undone along with it.
+            self.remove_tile_at(pt, tile, freq);
+            let idx = self.cell_at(pt.x, pt.y);
+            if !self.cells[idx].contradiction_check() {
+                self.push_entropy(pt);
+                if self.propagate(freq) {
+                    return true;
+                }
             }
+            self.tile_removals.clear();
         }
+
+        false
     }
 
     /// Keeps propagating consequences until there are none (think like it's a sudoku game).
@@ -125,32 +247,31 @@ impl Wave {
                 }
 
                 let compatible_tiles = self.get_compatible_dir(removal_update.tile, dir);
-                let neighbor_cell = &mut self.cells[neighbor_idx];
 
                 for compat in compatible_tiles.iter() {
                     let j = opposite_idx(i); // Opposite direction to i
+                    let neighbor_cell = &self.cells[neighbor_idx];
 
-                    if neighbor_cell.enabler_count[*compat].by_direction[j] == 1 {
-                        if neighbor_cell.possible[*compat] {
-                            neighbor_cell.remove_tile(*compat, freq);
-                            if neighbor_cell.contradiction_check() {
-                                println!("Contradiction!");
-                                return false;
-                            }
-                            self.entropy_queue.push(CoordEntropy {
-                                entropy: Entropy {
-                                    entropy: neighbor_cell.entropy(),
-                                    noise: neighbor_cell.entropy_noise,
-                                },
-                                coord: neighbor_coord,
-                            });
-                            self.tile_removals.push(RemovalUpdate {
+                    if neighbor_cell.enabler_count[*compat].by_direction[j] == 1
+                        && neighbor_cell.possible[*compat]
+                    {
+                        self.remove_tile_at(neighbor_coord, *compat, freq);
+                        if self.cells[neighbor_idx].contradiction_check() {
+                            self.stats.contradictions += 1;
+                            return false;
+                        }
+                        self.push_entropy(neighbor_coord);
+                    }
+                    self.cells[neighbor_idx].enabler_count[*compat].by_direction[j] -= 1;
+                    if let Some(collapse) = self.history.back_mut() {
+                        collapse.changes.push(Change::Enabler(
+                            RemovalUpdate {
                                 tile: *compat,
                                 coord: neighbor_coord,
-                            });
-                        }
+                            },
+                            j,
+                        ));
                     }
-                    neighbor_cell.enabler_count[*compat].by_direction[j] -= 1;
                 }
             }
         }
@@ -164,6 +285,21 @@ pub struct RemovalUpdate {
     coord: Point,
 }
 
+/// A change to a cell, kept so it can be undone.
+#[derive(Debug, Clone)]
+enum Change {
+    Removal(RemovalUpdate),        // The tile was removed from the cell.
+    Enabler(RemovalUpdate, usize), // The tile lost an enabler on the direction (index).
+}
+
+/// A collapsed cell, and every change propagated from it.
+#[derive(Debug, Clone)]
+struct Collapse {
+    coord: Point,
+    tile: usize,
+    changes: Vec<Change>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 struct Entropy {
     entropy: f32,
@@ -0,0 +1,113 @@
+use super::{
+    log::Log, map_gen::Map, state::RunSeed, EquipSlot, Equipable, Equipment, Experience, Name,
+    RunStats,
+};
+use specs::prelude::*;
+use std::fs::{self, File};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/*
+ *
+ * morgue.rs
+ * ---------
+ * Sums up a finished run: shown on the death screen and written to a plain-text morgue file.
+ *
+ */
+
+pub const MORGUE_DIR: &str = "./morgue";
+const LAST_MESSAGES: usize = 15; // Of the log, written to the morgue file.
+
+/// Lines describing the run: who died, how, where and when, what they killed and what they wore.
+pub fn summary(ecs: &World) -> Vec<String> {
+    let player = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let run_stats = ecs.read_storage::<RunStats>();
+    let experience = ecs.read_storage::<Experience>();
+    let equipment = ecs.read_storage::<Equipment>();
+    let equipables = ecs.read_storage::<Equipable>();
+    let map = ecs.fetch::<Map>();
+    let stats = run_stats.get(*player).cloned().unwrap_or_default();
+
+    let mut lines = Vec::new();
+    lines.push(format!(
+        "{}, level {}",
+        names.get(*player).map_or("Wanderer", |n| &n.name),
+        experience.get(*player).map_or(1, |exp| exp.level)
+    ));
+    lines.push(
+        stats
+            .cause_of_death
+            .unwrap_or_else(|| "Died of unknown causes.".to_string()),
+    );
+    lines.push(format!(
+        "Died on level {} after {} turns.",
+        map.level + 1,
+        stats.turns
+    ));
+    lines.push(format!("Seed: {:x}", ecs.fetch::<RunSeed>().0));
+
+    lines.push(String::new());
+    lines.push("KILLS".to_string());
+    if stats.kills.is_empty() {
+        lines.push("  None".to_string());
+    }
+    for (mob_type, count) in stats.kills.iter() {
+        lines.push(format!("  {:<12}{}", mob_type, count));
+    }
+
+    lines.push(String::new());
+    lines.push("EQUIPMENT".to_string());
+    let mut equipped: Vec<(EquipSlot, &str)> = (&equipment, &equipables, &names)
+        .join()
+        .filter(|(equip, _, _)| equip.user == *player)
+        .map(|(_, equipable, name)| (equipable.slot, name.name.as_str()))
+        .collect();
+    equipped.sort_by_key(|(slot, _)| *slot as i32);
+    if equipped.is_empty() {
+        lines.push("  None".to_string());
+    }
+    for (slot, name) in equipped {
+        lines.push(format!("  {:<12}{}", slot_name(slot), name));
+    }
+
+    lines
+}
+
+fn slot_name(slot: EquipSlot) -> &'static str {
+    match slot {
+        EquipSlot::Weapon1 => "Melee",
+        EquipSlot::Weapon2 => "Missile",
+        EquipSlot::Head => "Head",
+        EquipSlot::Torso => "Torso",
+        EquipSlot::Hands => "Hands",
+        EquipSlot::Legs => "Legs",
+        EquipSlot::Feet => "Feet",
+        EquipSlot::Back => "Back",
+        EquipSlot::Floating => "Floating",
+    }
+}
+
+/// Writes the summary of the run, followed by the last log messages, to a new file in
+/// MORGUE_DIR. Returns its path.
+pub fn write_morgue(ecs: &World) -> std::io::Result<String> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = format!("{}/{:x}-{}.txt", MORGUE_DIR, ecs.fetch::<RunSeed>().0, secs);
+
+    fs::create_dir_all(MORGUE_DIR)?;
+    let mut file = File::create(&path)?;
+    for line in summary(ecs) {
+        writeln!(file, "{}", line)?;
+    }
+    writeln!(file)?;
+    writeln!(file, "LAST MESSAGES")?;
+    let log = ecs.fetch::<Log>();
+    let skip = log.messages.len().saturating_sub(LAST_MESSAGES);
+    for (msg, _) in log.messages.iter().skip(skip) {
+        writeln!(file, "  {}", msg)?;
+    }
+
+    Ok(path)
+}
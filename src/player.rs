@@ -1,9 +1,12 @@
 use super::{
     map_gen::{common::count_neighbor_tile_entity, Map, TileType},
+    systems::initiative::{melee_cost, missile_cost, move_cost, ACTION_COST, RELOAD_COST},
+    systems::missile::hit_chance,
     utils::directions::*,
-    ActiveWeapon, CollectItem, Container, EquipSlot, Equipable, Equipment, Fov, Item, MeleeAttack,
-    MissileAttack, MissileWeapon, Mob, Player, Position, RunState, SelectedPosition, Target,
-    TryReload,
+    ActiveWeapon, BaseStats, CollectItem, Container, Energy, EquipSlot, Equipable, Equipment,
+    Experience, Explosive, Fov, Item, LevelUpChoice, MeleeAttack, MeleeWeapon, MissileAttack,
+    MissileWeapon, Mob, Player, Position, RunState, RunStats, SelectedPosition, Skills,
+    StatusEffects, StatusType, Survival, Target, ThrowAttack, TryReload, SURVIVAL_MAX,
 };
 use crate::log::Log;
 use crate::utils::colors::*;
@@ -19,6 +22,8 @@ use std::cmp::Ordering;
  *
  */
 
+pub const LEVEL_UP_HP: i32 = 5; // Max HP gained when choosing health on level up.
+
 /// Tries to move the player, performing melee attacks if needed.
 pub fn move_player(dir: Direction, ecs: &mut World) {
     let mut pos_ = ecs.write_storage::<Position>();
@@ -95,6 +100,174 @@ fn can_shoot(ecs: &World, ent: Entity) -> bool {
     false
 }
 
+/// Stunned players lose their turn.
+pub fn player_stunned(ecs: &mut World) -> bool {
+    let player = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let stunned = matches!(statuses.get(*player), Some(s) if s.has(StatusType::Stunned));
+
+    if stunned {
+        let mut log = ecs.fetch_mut::<Log>();
+        log.add("You are stunned!", color("BrightWhite", 1.0));
+    }
+
+    stunned
+}
+
+/// Sits down to rest, recovering fatigue every turn until rested, hurt or interrupted. Not
+/// possible with enemies in sight.
+pub fn rest(ecs: &mut World) -> RunState {
+    if !visible_targets(ecs, false).is_empty() {
+        let mut log = ecs.fetch_mut::<Log>();
+        log.add(
+            "You can't rest with enemies nearby.",
+            color("BrightWhite", 1.0),
+        );
+        return RunState::Waiting;
+    }
+
+    let player = ecs.fetch::<Entity>();
+    let mut survival = ecs.write_storage::<Survival>();
+    let mut log = ecs.fetch_mut::<Log>();
+    match survival.get_mut(*player) {
+        Some(meters) if meters.fatigue < SURVIVAL_MAX => {
+            meters.resting = true;
+            log.add("You sit down to rest.", color("BrightWhite", 1.0));
+            RunState::PlayerTurn
+        }
+        _ => {
+            log.add("You aren't tired.", color("BrightWhite", 1.0));
+            RunState::Waiting
+        }
+    }
+}
+
+pub fn player_resting(ecs: &World) -> bool {
+    let player = ecs.fetch::<Entity>();
+    let survival = ecs.read_storage::<Survival>();
+
+    matches!(survival.get(*player), Some(s) if s.resting)
+}
+
+/// Rests for another turn, unless `interrupted` (by a key press) or an enemy comes into view.
+pub fn keep_resting(ecs: &mut World, interrupted: bool) -> RunState {
+    if !interrupted && visible_targets(ecs, false).is_empty() {
+        return RunState::PlayerTurn;
+    }
+
+    let player = ecs.fetch::<Entity>();
+    let mut survival = ecs.write_storage::<Survival>();
+    if let Some(meters) = survival.get_mut(*player) {
+        meters.resting = false;
+    }
+    let mut log = ecs.fetch_mut::<Log>();
+    log.add("You stop resting.", color("BrightWhite", 1.0));
+
+    RunState::Waiting
+}
+
+/// Spends the energy the action the player just took costs, and counts the turn.
+pub fn spend_energy(ecs: &World) {
+    let player = ecs.fetch::<Entity>();
+    let map = ecs.fetch::<Map>();
+    let positions = ecs.read_storage::<Position>();
+    let equipment = ecs.read_storage::<Equipment>();
+    let active_wpn = ecs.read_storage::<ActiveWeapon>();
+    let melee_wpns = ecs.read_storage::<MeleeWeapon>();
+    let missile_wpns = ecs.read_storage::<MissileWeapon>();
+    let mut energies = ecs.write_storage::<Energy>();
+
+    let cost = if ecs.read_storage::<MeleeAttack>().contains(*player) {
+        melee_cost(
+            (&equipment, &active_wpn, &melee_wpns)
+                .join()
+                .find(|(equip, _, _)| equip.user == *player)
+                .map(|(_, _, melee)| melee.class),
+        )
+    } else if ecs.read_storage::<MissileAttack>().contains(*player)
+        || ecs.read_storage::<ThrowAttack>().contains(*player)
+    {
+        (&equipment, &active_wpn, &missile_wpns)
+            .join()
+            .find(|(equip, _, _)| equip.user == *player)
+            .map_or(ACTION_COST, |(_, _, missile)| missile_cost(missile.class))
+    } else if ecs.read_storage::<TryReload>().contains(*player) {
+        RELOAD_COST
+    } else {
+        positions
+            .get(*player)
+            .map_or(ACTION_COST, |pos| move_cost(&map, *pos))
+    };
+
+    if let Some(energy) = energies.get_mut(*player) {
+        energy.energy -= cost;
+    }
+    if let Some(stats) = ecs.write_storage::<RunStats>().get_mut(*player) {
+        stats.turns += 1;
+    }
+}
+
+/// Whether the player has enough energy to act.
+pub fn player_ready(ecs: &World) -> bool {
+    let player = ecs.fetch::<Entity>();
+    let energies = ecs.read_storage::<Energy>();
+
+    energies.get(*player).map_or(true, |energy| energy.ready())
+}
+
+/// Whether any mob on the level still has enough energy to act.
+pub fn mobs_ready(ecs: &World) -> bool {
+    let mobs = ecs.read_storage::<Mob>();
+    let positions = ecs.read_storage::<Position>();
+    let energies = ecs.read_storage::<Energy>();
+
+    (&mobs, &positions, &energies)
+        .join()
+        .any(|(_, _, energy)| energy.ready())
+}
+
+/// Whether the player has enough XP for a new level.
+pub fn can_level_up(ecs: &World) -> bool {
+    let player = ecs.fetch::<Entity>();
+    let experience = ecs.read_storage::<Experience>();
+
+    matches!(experience.get(*player), Some(exp) if exp.can_level_up())
+}
+
+/// Raises the player's level and the chosen stat.
+pub fn level_up(ecs: &World, choice: LevelUpChoice) {
+    let player = ecs.fetch::<Entity>();
+    let mut experience = ecs.write_storage::<Experience>();
+    let mut stats = ecs.write_storage::<BaseStats>();
+    let mut log = ecs.fetch_mut::<Log>();
+
+    let (exp, stats) = match (experience.get_mut(*player), stats.get_mut(*player)) {
+        (Some(exp), Some(stats)) => (exp, stats),
+        _ => return,
+    };
+    exp.level += 1;
+    let gain = match choice {
+        LevelUpChoice::Health => {
+            stats.health.max_hp += LEVEL_UP_HP;
+            stats.health.hp += LEVEL_UP_HP;
+            format!("{} max HP", LEVEL_UP_HP)
+        }
+        LevelUpChoice::Defense => {
+            stats.defense += 1;
+            "1 defense".to_string()
+        }
+        LevelUpChoice::Attack => {
+            stats.attack_bonus += 1;
+            "1 damage".to_string()
+        }
+    };
+
+    log.add(
+        format!("You are now level {}, gaining {}.", exp.level, gain),
+        color("BrightYellow", 1.0),
+    );
+}
+
 /// Cycles between the player's visible targets.
 pub fn choose_target(ecs: &mut World, up: bool) -> RunState {
     let player = ecs.fetch::<Entity>();
@@ -109,6 +282,29 @@ pub fn choose_target(ecs: &mut World, up: bool) -> RunState {
     }
 
     let vis_targets = visible_targets(ecs, true);
+
+    // Explosives are thrown at a tile instead.
+    let explosives = ecs.read_storage::<Explosive>();
+    if let Some(explosive) =
+        get_weapon(ecs, *player, EquipSlot::Weapon2).and_then(|w| explosives.get(w))
+    {
+        let positions = ecs.read_storage::<Position>();
+        let target = vis_targets
+            .iter()
+            .find(|t| !t.2)
+            .and_then(|t| positions.get(t.0))
+            .copied()
+            .unwrap_or(*ecs.fetch::<Point>());
+        log.add(
+            "Aim with the direction keys, [F] to throw, [Esc] to cancel.",
+            color("BrightWhite", 1.0),
+        );
+        return RunState::AimArea {
+            target,
+            radius: explosive.radius,
+        };
+    }
+
     let mut targets = ecs.write_storage::<Target>();
     let entities = ecs.entities();
 
@@ -138,7 +334,7 @@ pub fn choose_target(ecs: &mut World, up: bool) -> RunState {
         if !up && idx > 0 {
             let tgt = vis_targets[idx - 1];
             targets
-                .insert(tgt.0, Target { covered: tgt.2 })
+                .insert(tgt.0, new_target(ecs, tgt.0, tgt.2))
                 .expect("Insert fail");
         } else {
             if idx + 1 > vis_targets.len() - 1 {
@@ -148,7 +344,7 @@ pub fn choose_target(ecs: &mut World, up: bool) -> RunState {
             }
             let tgt = vis_targets[idx];
             targets
-                .insert(tgt.0, Target { covered: tgt.2 })
+                .insert(tgt.0, new_target(ecs, tgt.0, tgt.2))
                 .expect("Insert fail");
         }
     } else {
@@ -157,9 +353,7 @@ pub fn choose_target(ecs: &mut World, up: bool) -> RunState {
         targets
             .insert(
                 first_target.0,
-                Target {
-                    covered: first_target.2,
-                },
+                new_target(ecs, first_target.0, first_target.2),
             )
             .expect("Insert fail");
     }
@@ -167,6 +361,41 @@ pub fn choose_target(ecs: &mut World, up: bool) -> RunState {
     RunState::Targeting
 }
 
+/// Targets `target`, with the player's chance of hitting it with the readied missile weapon.
+fn new_target(ecs: &World, target: Entity, covered: bool) -> Target {
+    let player = ecs.fetch::<Entity>();
+    let map = ecs.fetch::<Map>();
+    let positions = ecs.read_storage::<Position>();
+    let stats = ecs.read_storage::<BaseStats>();
+    let missile_wpn = ecs.read_storage::<MissileWeapon>();
+    let skills = ecs.read_storage::<Skills>();
+    let survival = ecs.read_storage::<Survival>();
+
+    let wpn = get_weapon(ecs, *player, EquipSlot::Weapon2).and_then(|w| missile_wpn.get(w));
+    let range = wpn.map_or(0, |w| w.stats.range);
+    let skill = match (wpn, skills.get(*player)) {
+        (Some(w), Some(s)) => s.missile_level(w.class),
+        _ => 0,
+    };
+    let penalty = survival.get(*player).map_or(0, |s| s.hit_penalty());
+    let hit_chance = match (
+        positions.get(*player),
+        positions.get(target),
+        stats.get(*player),
+        stats.get(target),
+    ) {
+        (Some(from), Some(to), Some(attacker), Some(defender)) => {
+            hit_chance(&map, *from, *to, range, attacker, defender, skill, penalty)
+        }
+        _ => 0,
+    };
+
+    Target {
+        covered,
+        hit_chance,
+    }
+}
+
 /// Performs a missile (ranged) attack to the selected entity.
 pub fn missile_attack(ecs: &mut World) {
     let entities = ecs.entities();
@@ -192,6 +421,39 @@ pub fn missile_attack(ecs: &mut World) {
     targets.clear();
 }
 
+/// Moves the aim of a thrown explosive, as far as the weapon's range.
+pub fn move_aim(ecs: &World, target: Point, radius: i32, dir: Direction) -> RunState {
+    let player = ecs.fetch::<Entity>();
+    let ppos = *ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let missile_wpn = ecs.read_storage::<MissileWeapon>();
+
+    let range = get_weapon(ecs, *player, EquipSlot::Weapon2)
+        .and_then(|w| missile_wpn.get(w))
+        .map_or(0, |w| w.stats.range);
+    let next = target + dir;
+    if map.in_map_bounds(next) && DistanceAlg::Pythagoras.distance2d(ppos, next) <= range as f32 {
+        return RunState::AimArea {
+            target: next,
+            radius,
+        };
+    }
+
+    RunState::AimArea { target, radius }
+}
+
+/// Throws the readied explosive at the aimed tile.
+pub fn throw_attack(ecs: &World, target: Point) -> RunState {
+    let player = ecs.fetch::<Entity>();
+    let mut throw_attack = ecs.write_storage::<ThrowAttack>();
+
+    throw_attack
+        .insert(*player, ThrowAttack { target })
+        .expect("Throw attack insertion failed");
+
+    RunState::PlayerTurn
+}
+
 /// Cancels targeting, returning a Waiting state.
 pub fn reset_targeting(ecs: &mut World) -> RunState {
     let mut targets = ecs.write_storage::<Target>();
@@ -219,17 +481,8 @@ fn visible_targets(ecs: &World, hittable: bool) -> Vec<(Entity, f32, bool)> {
                         let mobpos = Point::new(pos.x, pos.y);
                         let player_pos = positions.get(*player_ent).unwrap();
                         let ppos = Point::new(player_pos.x, player_pos.y);
-                        let mut covered = false;
-                        if hittable {
-                            let points = line2d_vector(ppos, mobpos);
-                            for pt in points.iter().take(points.len() - 1) {
-                                let i = map.idx(pt.x, pt.y);
-                                // if there's a blocker in the aim line, you can't hit the entity.
-                                if map.tiles[i].block {
-                                    covered = true;
-                                }
-                            }
-                        }
+                        // Entities behind opaque tiles can't be hit.
+                        let covered = hittable && map.cover_between(ppos, mobpos).is_none();
                         let dist = DistanceAlg::Pythagoras.distance2d(mobpos, ppos);
                         visible_targets.push((*ent, dist, covered));
                     }
@@ -319,6 +572,10 @@ pub fn context_action(ecs: &mut World) -> RunState {
     if map.is_exit(map.idx_pt(ppos)) {
         return RunState::NextLevel;
     }
+    // And over the "<", go back to the previous one.
+    if map.is_exit_up(map.idx_pt(ppos)) {
+        return RunState::PreviousLevel;
+    }
 
     let tile_list = vec![TileType::OpenDoor, TileType::ClosedDoor];
     let possible_count_dir = count_neighbor_tile_entity(&map, ppos, tile_list, true);
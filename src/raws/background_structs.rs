@@ -0,0 +1,18 @@
+use super::Attributes;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct Background {
+    pub name: String,
+    pub descr: String,
+    pub stats: BackgroundStats,
+    pub equips: Vec<String>, // Equipped from the start; the first weapon is the active one.
+    pub inventory: Vec<String>, // Carried, but not equipped.
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BackgroundStats {
+    pub max_hp: i32,
+    pub defense: i32,
+    pub attributes: Attributes,
+}
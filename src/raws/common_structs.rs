@@ -8,3 +8,10 @@ pub struct Renderable {
     pub bg: String,
     pub layer: i32,
 }
+
+#[derive(Deserialize, Debug)]
+pub struct OnHit {
+    pub status: String, // "bleeding", "poisoned", "stunned" or "burning".
+    pub turns: i32,
+    pub chance: i32, // %
+}
@@ -0,0 +1,9 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+pub struct Faction {
+    pub name: String,    // A MobType, or "Player".
+    pub default: String, // Reaction to the factions not in `relations`.
+    pub relations: HashMap<String, String>,
+}
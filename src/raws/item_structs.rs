@@ -1,4 +1,4 @@
-use super::Renderable;
+use super::{OnHit, Renderable};
 use serde::Deserialize;
 use std::collections::HashMap;
 
@@ -24,16 +24,21 @@ pub struct Consumable {
 #[derive(Deserialize, Debug)]
 pub struct Melee {
     pub damage: String,
+    pub damage_type: String,
     pub class: String,
+    pub on_hit: Option<OnHit>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Missile {
     pub damage: String,
+    pub damage_type: String,
     pub range: i32,
     pub class: String,
     pub ammo_type: String,
     pub max_ammo: i32,
+    pub blast_radius: Option<i32>, // Explodes where it lands (grenades).
+    pub on_hit: Option<OnHit>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -45,6 +50,7 @@ pub struct Ammunition {
 #[derive(Deserialize, Debug)]
 pub struct Armor {
     pub defense: i32,
+    pub resistances: Option<HashMap<String, i32>>, // Damage type -> resistance (< 0: weakness).
 }
 
 #[derive(Deserialize, Debug)]
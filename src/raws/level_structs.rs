@@ -0,0 +1,125 @@
+use crate::map_gen::{CustomRegion, MapType, Symmetry, TileType};
+use serde::Deserialize;
+
+/*
+ * A level recipe is a list of generation steps, applied in order to the same map.
+ * See resources/raws/levels.ron.
+ */
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LevelRecipe {
+    pub name: String,
+    pub depth: (i32, i32), // Min and max depth (inclusive) where this recipe can be used.
+    pub maptype: MapType,
+    pub spawn: Option<(i32, i32)>, // None -> Random walkable tile.
+    pub steps: Vec<LevelStep>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LevelStep {
+    pub step: Step,
+    #[serde(default)]
+    pub region: RecipeRegion,
+    pub chance: Option<i32>, // Chance (%) of applying this step. None -> Always.
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub enum RecipeRegion {
+    #[default]
+    Map,
+    Rect(i32, i32, i32, i32), // x, y, width, height
+    Circle(i32, i32, i32),    // x, y (top-left corner of the bounding box), radius
+}
+
+impl RecipeRegion {
+    /// The region as used by the generators. None -> The whole map.
+    pub fn to_custom_region(&self) -> Option<CustomRegion> {
+        match *self {
+            RecipeRegion::Map => None,
+            RecipeRegion::Rect(x, y, w, h) => Some(CustomRegion::new_rect(x, y, w, h)),
+            RecipeRegion::Circle(x, y, r) => Some(CustomRegion::new_circ(x, y, r)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum Step {
+    // Generators.
+    Bsp,
+    BspRuin,
+    Cave,
+    TightCave,
+    Forest,
+    Digger,
+    DiggerInverted,
+    Prefab(String), // Template name, as in resources/rex.
+    Wfc {
+        template: String,
+        size: (i32, i32), // Template width and height.
+        tile_size: i32,
+        mix_match: bool,
+        #[serde(default)]
+        symmetry: Symmetry, // Orientations of the template used (None, Mirror or All).
+    },
+    // WFC inside each of the rooms generated so far. Rooms of at least `min_room` always get it,
+    // the others with a chance of `room_chance` (%).
+    WfcRooms {
+        template: String,
+        size: (i32, i32),
+        tile_size: i32,
+        mix_match: bool,
+        #[serde(default)]
+        symmetry: Symmetry,
+        room_chance: i32,
+        min_room: Option<(i32, i32)>,
+    },
+    // Applies only one of the steps, chosen at random.
+    OneOf(Vec<LevelStep>),
+
+    // Post-passes.
+    ConnectRegions {
+        natural: bool,
+    },
+    AddVegetation {
+        trees: bool,
+    },
+    AddDoors {
+        chance: i32,
+    },
+    MakeLake {
+        liquid: TileType,
+        tiles: u32,
+    },
+}
+
+impl Step {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Step::Bsp => "Bsp",
+            Step::BspRuin => "BspRuin",
+            Step::Cave => "Cave",
+            Step::TightCave => "TightCave",
+            Step::Forest => "Forest",
+            Step::Digger => "Digger",
+            Step::DiggerInverted => "DiggerInverted",
+            Step::Prefab(_) => "Prefab",
+            Step::Wfc { .. } => "Wfc",
+            Step::WfcRooms { .. } => "WfcRooms",
+            Step::OneOf(_) => "OneOf",
+            Step::ConnectRegions { .. } => "ConnectRegions",
+            Step::AddVegetation { .. } => "AddVegetation",
+            Step::AddDoors { .. } => "AddDoors",
+            Step::MakeLake { .. } => "MakeLake",
+        }
+    }
+
+    /// The template used by the step, if any.
+    pub fn template(&self) -> Option<&str> {
+        match self {
+            Step::Prefab(template)
+            | Step::Wfc { template, .. }
+            | Step::WfcRooms { template, .. } => Some(template),
+            _ => None,
+        }
+    }
+}
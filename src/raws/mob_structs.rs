@@ -1,4 +1,4 @@
-use super::Renderable;
+use super::{OnHit, Renderable};
 use serde::Deserialize;
 //use std::collections::HashMap;
 
@@ -11,7 +11,15 @@ pub struct Mob {
     pub fov_range: i32,
     pub blocker: bool,
     pub stats: Stats,
+    pub ai: Option<AI>,
     pub equips: Option<Equipment>,
+    pub inventory: Option<Vec<String>>, // Carried, but not equipped (e.g. ammo).
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AI {
+    pub start: String, // "Idle", "Wander" or "Sleep".
+    pub flee_hp: i32,  // % of max_hp.
 }
 
 #[derive(Deserialize, Debug)]
@@ -19,8 +27,20 @@ pub struct Stats {
     pub hp: i32,
     pub max_hp: i32,
     pub attack: String,
+    pub damage_type: Option<String>, // Of the unarmed attack, "blunt" if not given.
     pub attack_range: i32,
     pub defense: i32,
+    pub difficulty: i32,                // Scales the XP given for killing it.
+    pub speed: Option<i32>,             // Energy gained per tick, 100 (the player's) if not given.
+    pub attributes: Option<Attributes>, // All 5 (average) if not given.
+    pub on_hit: Option<OnHit>,          // Of the unarmed attack.
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Attributes {
+    pub strength: i32,
+    pub agility: i32,
+    pub perception: i32,
 }
 
 #[derive(Deserialize, Debug)]
@@ -20,13 +20,26 @@ mod furniture_structs;
 pub use furniture_structs::*;
 mod spawn_structs;
 pub use spawn_structs::*;
+mod faction_structs;
+pub use faction_structs::*;
+mod background_structs;
+pub use background_structs::*;
+mod level_structs;
+pub use level_structs::*;
+mod rawlevels;
+pub use rawlevels::*;
 
 embedded_resource!(RAW_COLORS, "../../resources/raws/colors.ron");
 embedded_resource!(RAW, "../../resources/raws/raws.ron");
+embedded_resource!(RAW_LEVELS, "../../resources/raws/levels.ron");
+
+// Level recipes are read from here first, so they can be changed without recompiling.
+const LEVELS_PATH: &str = "resources/raws/levels.ron";
 
 lazy_static! {
     pub static ref COLORS: Mutex<RawColors> = Mutex::new(RawColors::empty());
     pub static ref RAWS: Mutex<RawMaster> = Mutex::new(RawMaster::empty());
+    pub static ref LEVELS: Mutex<RawLevels> = Mutex::new(RawLevels::empty());
 }
 
 #[derive(Deserialize, Debug)]
@@ -36,6 +49,13 @@ pub struct Raws {
     pub containers: Vec<Container>,
     pub furnitures: Vec<Furniture>,
     pub spawn_table: Vec<SpawnTable>,
+    pub factions: Vec<Faction>,
+    pub backgrounds: Vec<Background>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Levels {
+    pub levels: Vec<LevelRecipe>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -46,6 +66,7 @@ pub struct Colors {
 pub fn load_raws() {
     link_resource!(RAW_COLORS, "resources/colors.ron");
     link_resource!(RAW, "resources/raws.ron");
+    link_resource!(RAW_LEVELS, "resources/levels.ron");
 
     let raw_string_colors = get_raw_string("resources/colors.ron".to_string());
     let raw_string_etc = get_raw_string("resources/raws.ron".to_string());
@@ -64,6 +85,20 @@ pub fn load_raws() {
 
     let decoder: Raws = ron::de::from_str(&raw_string_etc).expect("Unable to parse RON.");
     RAWS.lock().unwrap().load(decoder);
+
+    load_levels();
+}
+
+/// Loads the level recipes from LEVELS_PATH if it exists, or from the embedded copy otherwise.
+pub fn load_levels() {
+    let raw_string_levels = match std::fs::read_to_string(LEVELS_PATH) {
+        Ok(s) => s,
+        Err(_) => get_raw_string("resources/levels.ron".to_string()).to_string(),
+    };
+
+    let decoder_levels: Levels = ron::de::from_str(&raw_string_levels)
+        .unwrap_or_else(|e| panic!("Unable to parse the level recipes: {}", e));
+    LEVELS.lock().unwrap().load(decoder_levels);
 }
 
 fn get_raw_string(path: String) -> &'static str {
@@ -0,0 +1,83 @@
+use super::{LevelRecipe, LevelStep, Levels, Step};
+use crate::rexloader;
+use crate::{MAP_HEIGHT, MAP_WIDTH};
+use bracket_lib::prelude::RandomNumberGenerator;
+
+pub struct RawLevels {
+    pub raws: Levels,
+}
+
+impl RawLevels {
+    pub fn empty() -> Self {
+        RawLevels {
+            raws: Levels { levels: Vec::new() },
+        }
+    }
+
+    pub fn load(&mut self, raws: Levels) {
+        // A bad region or template would otherwise only crash the generator on the level that
+        // happens to use it.
+        for recipe in raws.levels.iter() {
+            validate_steps(&recipe.name, "", &recipe.steps);
+        }
+        self.raws = raws;
+    }
+
+    /// Picks one of the recipes available at `depth`. When no recipe covers it (e.g. too deep),
+    /// the deepest recipes are used instead.
+    pub fn get_recipe(&self, depth: i32, rng: &mut RandomNumberGenerator) -> Option<LevelRecipe> {
+        let mut recipes: Vec<&LevelRecipe> = self
+            .raws
+            .levels
+            .iter()
+            .filter(|r| depth >= r.depth.0 && depth <= r.depth.1)
+            .collect();
+
+        if recipes.is_empty() {
+            let deepest = self.raws.levels.iter().map(|r| r.depth.1).max()?;
+            recipes = self
+                .raws
+                .levels
+                .iter()
+                .filter(|r| r.depth.1 == deepest)
+                .collect();
+        }
+
+        rng.random_slice_entry(&recipes).map(|r| (*r).clone())
+    }
+}
+
+/// Checks that every step's region fits in the level and its template was loaded by rexloader.
+/// Panics naming the recipe and the step (e.g. "step 2.1" for the first choice of a OneOf).
+fn validate_steps(recipe: &str, prefix: &str, steps: &[LevelStep]) {
+    for (i, step) in steps.iter().enumerate() {
+        let id = format!("{}{}", prefix, i + 1);
+        if let Some(region) = step.region.to_custom_region() {
+            if !region.fits_in(MAP_WIDTH, MAP_HEIGHT) {
+                panic!(
+                    "level recipe \"{}\", step {} ({}): region {:?} doesn't fit in the {}x{} level",
+                    recipe,
+                    id,
+                    step.step.name(),
+                    step.region,
+                    MAP_WIDTH,
+                    MAP_HEIGHT
+                );
+            }
+        }
+        if let Some(template) = step.step.template() {
+            if !rexloader::is_loaded(&format!("resources/{}.xp", template)) {
+                panic!(
+                    "level recipe \"{}\", step {} ({}): unknown template \"{}\"",
+                    recipe,
+                    id,
+                    step.step.name(),
+                    template
+                );
+            }
+        }
+        if let Step::OneOf(choices) = &step.step {
+            validate_steps(recipe, &format!("{}.", id), choices);
+        }
+    }
+}
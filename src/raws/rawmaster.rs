@@ -1,17 +1,22 @@
-use super::{common_structs, Raws};
+use super::{common_structs, Background, Raws};
 use crate::components::{
-    AmmoType, Ammunition, Armor, Attack, BaseStats, Blocker, Consumable, Container, Description,
-    EquipSlot, Equipable, Fov, Health, Item, MeleeWeapon, MeleeWeaponClass, MissileWeapon,
-    MissileWeaponClass, Mob, Name, Position, Renderable,
+    AIState, AmmoType, Ammunition, Armor, Attack, Attributes, BaseStats, Behavior, Blocker,
+    Consumable, Container, DamageType, Description, Energy, EquipSlot, Equipable, Explosive,
+    Faction, Fov, Health, Item, MeleeWeapon, MeleeWeaponClass, MissileWeapon, MissileWeaponClass,
+    Mob, MobType, Name, OnHit, Position, Reaction, Renderable, StatusType, TURN_ENERGY,
 };
 use crate::map_gen::map::MapType;
 use crate::spawner::SpawnTable;
 use crate::utils::colors::color;
-use bracket_lib::prelude::{parse_dice_string, to_cp437, ColorPair, RandomNumberGenerator};
+use crate::utils::dice::Dice;
+use bracket_lib::prelude::{to_cp437, ColorPair, RandomNumberGenerator};
 use specs::prelude::*;
 use std::collections::HashMap;
 use std::str::FromStr;
 
+// Consumable effects other than statuses.
+const CONSUMABLE_EFFECTS: [&str; 3] = ["heal", "food", "drink"];
+
 #[derive(Debug)]
 pub struct RawMaster {
     pub raws: Raws,
@@ -20,6 +25,8 @@ pub struct RawMaster {
     furniture_index: HashMap<String, usize>,
     mob_index: HashMap<String, usize>,
     spawn_index: HashMap<String, usize>,
+    faction_table: HashMap<String, (Reaction, HashMap<String, Reaction>)>,
+    background_index: HashMap<String, usize>,
 }
 
 impl RawMaster {
@@ -31,12 +38,16 @@ impl RawMaster {
                 furnitures: Vec::new(),
                 mobs: Vec::new(),
                 spawn_table: Vec::new(),
+                factions: Vec::new(),
+                backgrounds: Vec::new(),
             },
             item_index: HashMap::new(),
             container_index: HashMap::new(),
             furniture_index: HashMap::new(),
             mob_index: HashMap::new(),
             spawn_index: HashMap::new(),
+            faction_table: HashMap::new(),
+            background_index: HashMap::new(),
         }
     }
 
@@ -45,6 +56,43 @@ impl RawMaster {
 
         for (i, item) in self.raws.items.iter().enumerate() {
             self.item_index.insert(item.name.clone(), i);
+            // Bad damage strings and names are caught here rather than when the item first spawns.
+            if let Some(melee) = &item.melee {
+                parse_damage(&melee.damage, &item.name);
+                parse_name::<DamageType>(&melee.damage_type, "damage type", &item.name);
+                if let Some(on_hit) = &melee.on_hit {
+                    set_on_hit(on_hit, &item.name);
+                }
+            }
+            if let Some(missile) = &item.missile {
+                parse_damage(&missile.damage, &item.name);
+                parse_name::<DamageType>(&missile.damage_type, "damage type", &item.name);
+                parse_name::<AmmoType>(&missile.ammo_type, "ammo type", &item.name);
+                if let Some(on_hit) = &missile.on_hit {
+                    set_on_hit(on_hit, &item.name);
+                }
+            }
+            if let Some(consumable) = &item.consumable {
+                for effname in consumable.effects.keys() {
+                    if !CONSUMABLE_EFFECTS.contains(&effname.as_str()) {
+                        parse_name::<StatusType>(effname, "effect", &item.name);
+                    }
+                }
+            }
+            if let Some(ammo) = &item.ammunition {
+                parse_name::<AmmoType>(&ammo.ammo_type, "ammo type", &item.name);
+            }
+            if let Some(res) = item.armor.as_ref().and_then(|a| a.resistances.as_ref()) {
+                for dtype in res.keys() {
+                    let parsed: DamageType = parse_name(dtype, "damage type", &item.name);
+                    if parsed.is_deprivation() {
+                        panic!(
+                            "raws entry \"{}\": armor can't resist \"{}\" damage",
+                            item.name, dtype
+                        );
+                    }
+                }
+            }
         }
         for (i, container) in self.raws.containers.iter().enumerate() {
             self.container_index.insert(container.name.clone(), i);
@@ -54,10 +102,46 @@ impl RawMaster {
         }
         for (i, mob) in self.raws.mobs.iter().enumerate() {
             self.mob_index.insert(mob.name.clone(), i);
+            parse_damage(&mob.stats.attack, &mob.name);
+            parse_name::<MobType>(&mob.mob_type, "mob type", &mob.name);
+            if let Some(ai) = &mob.ai {
+                parse_name::<AIState>(&ai.start, "AI state", &mob.name);
+            }
+            if let Some(dtype) = &mob.stats.damage_type {
+                parse_name::<DamageType>(dtype, "damage type", &mob.name);
+            }
+            if let Some(on_hit) = &mob.stats.on_hit {
+                set_on_hit(on_hit, &mob.name);
+            }
         }
         for (i, spawn) in self.raws.spawn_table.iter().enumerate() {
             self.spawn_index.insert(spawn.name.clone(), i);
         }
+        for faction in self.raws.factions.iter() {
+            let relations = faction
+                .relations
+                .iter()
+                .map(|(other, reaction)| {
+                    let reaction = parse_name(reaction, "reaction", &faction.name);
+                    (other.clone(), reaction)
+                })
+                .collect();
+            let default = parse_name(&faction.default, "reaction", &faction.name);
+            self.faction_table
+                .insert(faction.name.clone(), (default, relations));
+        }
+        for (i, background) in self.raws.backgrounds.iter().enumerate() {
+            self.background_index.insert(background.name.clone(), i);
+            // A typo in a starting kit would otherwise only show up as a missing item.
+            for item in background.equips.iter().chain(background.inventory.iter()) {
+                if !self.item_index.contains_key(item) {
+                    panic!(
+                        "raws entry \"{}\": unknown item \"{}\"",
+                        background.name, item
+                    );
+                }
+            }
+        }
     }
 
     pub fn get_renderable(&self, name: &str) -> &Option<common_structs::Renderable> {
@@ -86,6 +170,33 @@ fn set_renderable(render: &common_structs::Renderable) -> Renderable {
     }
 }
 
+/// Parses a damage string from the raws, panicking with the entry's name if it's malformed.
+fn parse_damage(expr: &str, entry: &str) -> Dice {
+    expr.parse::<Dice>()
+        .unwrap_or_else(|e| panic!("{}", e.in_entry(entry)))
+}
+
+/// Parses a name (damage type, status, reaction...) from the raws, panicking with the entry's
+/// name if it's unknown.
+fn parse_name<T: FromStr>(value: &str, kind: &str, entry: &str) -> T {
+    T::from_str(value)
+        .unwrap_or_else(|_| panic!("raws entry \"{}\": unknown {} \"{}\"", entry, kind, value))
+}
+
+fn set_on_hit(on_hit: &common_structs::OnHit, entry: &str) -> OnHit {
+    OnHit {
+        status: parse_name(&on_hit.status, "status", entry),
+        turns: on_hit.turns,
+        chance: on_hit.chance,
+    }
+}
+
+pub fn get_background<'a>(name: &str, raws: &'a RawMaster) -> Option<&'a Background> {
+    raws.background_index
+        .get(name)
+        .map(|&i| &raws.raws.backgrounds[i])
+}
+
 pub fn get_random_possible_equips(
     name: &str,
     raws: &RawMaster,
@@ -127,6 +238,25 @@ pub fn get_random_possible_equips(
     None
 }
 
+/// How members of faction `mine` react to members of faction `theirs`.
+pub fn faction_reaction(mine: &str, theirs: &str, raws: &RawMaster) -> Reaction {
+    match raws.faction_table.get(mine) {
+        Some((default, relations)) => *relations.get(theirs).unwrap_or(default),
+        None => Reaction::Neutral,
+    }
+}
+
+pub fn get_mob_inventory(name: &str, raws: &RawMaster) -> Vec<String> {
+    if raws.mob_index.contains_key(name) {
+        let mob = &raws.raws.mobs[raws.mob_index[name]];
+        if let Some(inventory) = &mob.inventory {
+            return inventory.clone();
+        }
+    }
+
+    Vec::new()
+}
+
 pub fn get_items_tier(tier: u8, raws: &RawMaster) -> Vec<String> {
     let items = &raws.raws.items;
     items
@@ -234,15 +364,25 @@ pub fn spawn_item(
             ent = ent.with(set_renderable(renderable));
         }
         if let Some(consumable) = &item.consumable {
+            let mut heal = 0;
+            let mut food = 0;
+            let mut drink = 0;
+            let mut statuses = Vec::new();
             for effect in consumable.effects.iter() {
                 let effname = effect.0.as_str();
                 match effname {
-                    "heal" => {
-                        ent = ent.with(Consumable { heal: *effect.1 });
-                    }
-                    _ => return None,
+                    "heal" => heal = *effect.1,
+                    "food" => food = *effect.1,
+                    "drink" => drink = *effect.1,
+                    _ => statuses.push((parse_name(effname, "effect", &item.name), *effect.1)),
                 }
             }
+            ent = ent.with(Consumable {
+                heal,
+                statuses,
+                food,
+                drink,
+            });
         }
         if let Some(equip) = &item.equipable {
             match equip.slot.as_str() {
@@ -295,67 +435,82 @@ pub fn spawn_item(
             }
         }
         if let Some(melee) = &item.melee {
-            if let Ok(dicetype) = parse_dice_string(&melee.damage) {
-                let weapon_stats = Attack {
-                    base_damage: melee.damage.to_string(),
-                    dice_n: dicetype.n_dice,
-                    dice_faces: dicetype.die_type,
-                    dice_bonus: dicetype.bonus,
-                    range: 0,
-                };
-                match melee.class.as_str() {
-                    "dagger" => {
-                        ent = ent.with(MeleeWeapon {
-                            stats: weapon_stats,
-                            class: MeleeWeaponClass::Dagger,
-                        })
-                    }
-                    "axe" => {
-                        ent = ent.with(MeleeWeapon {
-                            stats: weapon_stats,
-                            class: MeleeWeaponClass::Axe,
-                        })
-                    }
-                    _ => return None,
+            let weapon_stats = Attack {
+                damage: parse_damage(&melee.damage, &item.name),
+                range: 0,
+                damage_type: parse_name(&melee.damage_type, "damage type", &item.name),
+                on_hit: melee.on_hit.as_ref().map(|o| set_on_hit(o, &item.name)),
+            };
+            match melee.class.as_str() {
+                "dagger" => {
+                    ent = ent.with(MeleeWeapon {
+                        stats: weapon_stats,
+                        class: MeleeWeaponClass::Dagger,
+                    })
                 }
+                "axe" => {
+                    ent = ent.with(MeleeWeapon {
+                        stats: weapon_stats,
+                        class: MeleeWeaponClass::Axe,
+                    })
+                }
+                _ => return None,
             }
         }
         if let Some(missile) = &item.missile {
-            if let Ok(dicetype) = parse_dice_string(&missile.damage) {
-                let weapon_stats = Attack {
-                    base_damage: missile.damage.to_string(),
-                    dice_n: dicetype.n_dice,
-                    dice_faces: dicetype.die_type,
-                    dice_bonus: dicetype.bonus,
-                    range: 0,
-                };
-
-                match missile.class.as_str() {
-                    "pistol" => {
-                        ent = ent.with(MissileWeapon {
-                            stats: weapon_stats,
-                            class: MissileWeaponClass::Pistol,
-                            ammo: Ammunition {
-                                max_ammo: missile.max_ammo,
-                                ammo: missile.max_ammo,
-                                ammo_type: AmmoType::from_str(&missile.ammo_type).unwrap(),
-                            },
-                        })
-                    }
-                    _ => return None,
+            let weapon_stats = Attack {
+                damage: parse_damage(&missile.damage, &item.name),
+                range: missile.range,
+                damage_type: parse_name(&missile.damage_type, "damage type", &item.name),
+                on_hit: missile.on_hit.as_ref().map(|o| set_on_hit(o, &item.name)),
+            };
+
+            match missile.class.as_str() {
+                "pistol" => {
+                    ent = ent.with(MissileWeapon {
+                        stats: weapon_stats,
+                        class: MissileWeaponClass::Pistol,
+                        ammo: Ammunition {
+                            max_ammo: missile.max_ammo,
+                            ammo: missile.max_ammo,
+                            ammo_type: parse_name(&missile.ammo_type, "ammo type", &item.name),
+                        },
+                    })
+                }
+                "grenade" => {
+                    ent = ent.with(MissileWeapon {
+                        stats: weapon_stats,
+                        class: MissileWeaponClass::Grenade,
+                        ammo: Ammunition {
+                            max_ammo: missile.max_ammo,
+                            ammo: missile.max_ammo,
+                            ammo_type: parse_name(&missile.ammo_type, "ammo type", &item.name),
+                        },
+                    })
                 }
+                _ => return None,
+            }
+            if let Some(radius) = missile.blast_radius {
+                ent = ent.with(Explosive { radius });
             }
         }
         if let Some(ammo) = &item.ammunition {
             ent = ent.with(Ammunition {
                 max_ammo: ammo.ammo,
                 ammo: ammo.ammo,
-                ammo_type: AmmoType::from_str(&ammo.ammo_type).unwrap(),
+                ammo_type: parse_name(&ammo.ammo_type, "ammo type", &item.name),
             })
         }
         if let Some(armor) = &item.armor {
+            let mut resistances = HashMap::new();
+            if let Some(res) = &armor.resistances {
+                for (dtype, amount) in res.iter() {
+                    resistances.insert(parse_name(dtype, "damage type", &item.name), *amount);
+                }
+            }
             ent = ent.with(Armor {
                 defense: armor.defense,
+                resistances,
             })
         }
 
@@ -406,7 +561,11 @@ pub fn spawn_mob(
         let mut ent = entity;
 
         ent = ent.with(Mob {
-            mob_type: mob.mob_type.parse().unwrap(),
+            mob_type: parse_name(&mob.mob_type, "mob type", &mob.name),
+            difficulty: mob.stats.difficulty,
+        });
+        ent = ent.with(Faction {
+            name: mob.mob_type.clone(),
         });
         ent = ent.with(Name {
             name: mob.name.clone(),
@@ -424,20 +583,35 @@ pub fn spawn_mob(
             ent = ent.with(Blocker {});
         }
 
-        let mut attack_stats = Attack {
-            base_damage: "1d3".to_string(),
-            dice_n: 1,
-            dice_faces: 3,
-            dice_bonus: 1,
-            range: 0,
+        let mut behavior = Behavior {
+            state: AIState::Wander,
+            rest: AIState::Wander,
+            flee_hp: 0,
+            last_seen: None,
         };
+        if let Some(ai) = &mob.ai {
+            behavior.state = parse_name(&ai.start, "AI state", &mob.name);
+            // Once woken up, a mob stays alert.
+            behavior.rest = match behavior.state {
+                AIState::Sleep => AIState::Idle,
+                state => state,
+            };
+            behavior.flee_hp = ai.flee_hp;
+        }
+        ent = ent.with(behavior);
+        ent = ent.with(Energy {
+            speed: mob.stats.speed.unwrap_or(TURN_ENERGY),
+            energy: 0,
+        });
 
-        if let Ok(dicetype) = parse_dice_string(&mob.stats.attack) {
-            attack_stats.base_damage = mob.stats.attack.to_string();
-            attack_stats.dice_n = dicetype.n_dice;
-            attack_stats.dice_faces = dicetype.die_type;
-            attack_stats.dice_bonus = dicetype.bonus;
-            attack_stats.range = mob.stats.attack_range;
+        let mut attack_stats = Attack {
+            damage: parse_damage(&mob.stats.attack, &mob.name),
+            range: mob.stats.attack_range,
+            damage_type: DamageType::Blunt,
+            on_hit: mob.stats.on_hit.as_ref().map(|o| set_on_hit(o, &mob.name)),
+        };
+        if let Some(dtype) = &mob.stats.damage_type {
+            attack_stats.damage_type = parse_name(dtype, "damage type", &mob.name);
         }
 
         ent = ent.with(BaseStats {
@@ -447,6 +621,16 @@ pub fn spawn_mob(
             },
             defense: mob.stats.defense,
             attack: attack_stats,
+            attack_bonus: 0,
+            attributes: mob
+                .stats
+                .attributes
+                .as_ref()
+                .map_or_else(Attributes::default, |a| Attributes {
+                    strength: a.strength,
+                    agility: a.agility,
+                    perception: a.perception,
+                }),
             god: false,
         });
 
@@ -1,6 +1,13 @@
 use super::{
-    map_gen::Map, raws::*, ui::*, utils::colors::*, Name, Position, Remains, Renderable, RunState,
-    Target, WINDOW_HEIGHT, WINDOW_WIDTH, X_OFFSET, Y_OFFSET,
+    map_gen::Map,
+    player::{can_level_up, level_up},
+    raws::*,
+    saveload,
+    systems::explosion::landing_point,
+    ui::*,
+    utils::colors::*,
+    Name, Position, Projectile, Remains, Renderable, RunState, Target, WINDOW_HEIGHT, WINDOW_WIDTH,
+    X_OFFSET, Y_OFFSET,
 };
 use bracket_lib::prelude::*;
 use specs::prelude::*;
@@ -13,6 +20,8 @@ use specs::prelude::*;
  *
  */
 
+const PROJECTILE_MS_PER_TILE: f32 = 15.0;
+
 pub struct Renderer<'a> {
     pub ecs: &'a World,
     pub term: &'a mut BTerm,
@@ -60,6 +69,20 @@ impl<'a> Renderer<'a> {
                 y_offset,
             );
             self.render_entitites(&mut draw_batch, show_map, min_x, min_y, x_offset, y_offset);
+            if let RunState::AimArea { target, radius } = self.state {
+                self.render_area_aim(
+                    &mut draw_batch,
+                    target,
+                    radius,
+                    min_x,
+                    min_y,
+                    x_offset,
+                    y_offset,
+                );
+            }
+            if !show_map {
+                self.render_projectiles(&mut draw_batch, min_x, min_y, x_offset, y_offset);
+            }
         }
 
         draw_batch.submit(0).expect("Batch error.");
@@ -131,6 +154,86 @@ impl<'a> Renderer<'a> {
         }
     }
 
+    /// Renders the path of a thrown explosive and its blast area around where it would land.
+    fn render_area_aim(
+        &mut self,
+        draw_batch: &mut DrawBatch,
+        target: Point,
+        radius: i32,
+        min_x: i32,
+        min_y: i32,
+        x_offset: i32,
+        y_offset: i32,
+    ) {
+        let ppos = *self.ecs.fetch::<Point>();
+        let to_screen = |pt: Point| Point::new(pt.x - min_x + x_offset, pt.y - min_y + y_offset);
+        let range = DistanceAlg::Pythagoras.distance2d(ppos, target).ceil() as i32;
+        let map = self.ecs.fetch::<Map>();
+        // The explosive lands short of the target if something opaque is in the way.
+        let center = landing_point(&map, ppos, target, range);
+
+        for y in center.y - radius..=center.y + radius {
+            for x in center.x - radius..=center.x + radius {
+                let pt = Point::new(x, y);
+                if map.in_map_bounds(pt)
+                    && DistanceAlg::Pythagoras.distance2d(center, pt) <= radius as f32
+                    && map.cover_between(center, pt).is_some()
+                {
+                    draw_batch.set_bg(to_screen(pt), color("BrightRed", 0.4));
+                }
+            }
+        }
+
+        let cursor = Renderable {
+            glyph: to_cp437('X'),
+            color: ColorPair::new(color("BrightRed", 1.0), color("Background", 1.0)),
+            layer: 2,
+        };
+        self.render_line_path(
+            draw_batch,
+            to_screen(ppos),
+            to_screen(target),
+            cursor,
+            center != target,
+        );
+    }
+
+    /// Renders the shots in flight over everything else, on the sparse console, moving them along
+    /// their paths as time goes by. They're deleted once they get to the end.
+    fn render_projectiles(
+        &mut self,
+        draw_batch: &mut DrawBatch,
+        min_x: i32,
+        min_y: i32,
+        x_offset: i32,
+        y_offset: i32,
+    ) {
+        let map = self.ecs.fetch::<Map>();
+        let mut projectiles = self.ecs.write_storage::<Projectile>();
+        let entities = self.ecs.entities();
+
+        draw_batch.target(1);
+        for (ent, projectile) in (&entities, &mut projectiles).join() {
+            projectile.elapsed += self.term.frame_time_ms;
+            let step = (projectile.elapsed / PROJECTILE_MS_PER_TILE) as usize;
+            match projectile.path.get(step) {
+                Some(pt) => {
+                    if map.in_map_bounds(*pt) && map.tiles[map.idx_pt(*pt)].visible {
+                        draw_batch.set(
+                            Point::new(pt.x - min_x + x_offset, pt.y - min_y + y_offset),
+                            ColorPair::new(projectile.fg, color("Background", 0.0)),
+                            projectile.glyph,
+                        );
+                    }
+                }
+                None => {
+                    entities.delete(ent).expect("Unable to delete projectile");
+                }
+            }
+        }
+        draw_batch.target(0);
+    }
+
     fn render_map(
         &mut self,
         draw_batch: &mut DrawBatch,
@@ -220,6 +323,12 @@ impl<'a> Renderer<'a> {
                             *render,
                             cover,
                         );
+                        let fg = if cover { "BrightBlack" } else { "BrightCyan" };
+                        draw_batch.print_color(
+                            Point::new(ent_x + x_offset + 1, ent_y + y_offset - 1),
+                            format!("{}%", _target.hit_chance),
+                            ColorPair::new(color(fg, 1.0), color("Background", 1.0)),
+                        );
                     }
                 }
             }
@@ -233,7 +342,12 @@ impl<'a> Renderer<'a> {
             RunState::Menu {
                 menu_selection: selection,
             } => {
-                let res = menu::main_menu(selection, false, self.term, draw_batch);
+                let res = menu::main_menu(
+                    selection,
+                    saveload::does_save_exist(),
+                    self.term,
+                    draw_batch,
+                );
                 match res {
                     menu::MenuResult::NoSelection { selected } => {
                         *write_state = RunState::Menu {
@@ -242,11 +356,10 @@ impl<'a> Renderer<'a> {
                     }
                     menu::MenuResult::Selected { selected } => match selected {
                         menu::MenuSelection::NewGame => {
-                            *write_state = RunState::Start;
+                            *write_state = RunState::CharacterCreation;
                         }
                         menu::MenuSelection::LoadGame => {
-                            // TODO
-                            *write_state = RunState::Start;
+                            *write_state = RunState::LoadGame;
                         }
                         menu::MenuSelection::Quit => {
                             ::std::process::exit(0);
@@ -254,10 +367,27 @@ impl<'a> Renderer<'a> {
                     },
                 }
             }
+            RunState::CharacterCreation => {
+                match character::show_character_creation(self.ecs, self.term, draw_batch) {
+                    character::CharacterResult::Editing => {}
+                    character::CharacterResult::Cancel => {
+                        *write_state = RunState::Menu {
+                            menu_selection: menu::MenuSelection::NewGame,
+                        };
+                    }
+                    character::CharacterResult::Done => *write_state = RunState::Start,
+                }
+            }
+            RunState::GameOver => {
+                game_over::show_game_over(self.ecs, draw_batch);
+            }
             _ => {
                 hud::boxes(draw_batch);
                 hud::name_stats(self.ecs, draw_batch);
                 hud::show_equipped(self.ecs, draw_batch);
+                hud::show_attributes(self.ecs, draw_batch);
+                hud::show_survival(self.ecs, draw_batch);
+                hud::show_seed(self.ecs, draw_batch);
                 hud::game_log(self.ecs, draw_batch);
                 let mouse_pos = self.term.mouse_pos();
 
@@ -291,7 +421,7 @@ impl<'a> Renderer<'a> {
                         } else if inventory_result == inventory::InventoryResult::DropItem
                             || inventory_result == inventory::InventoryResult::UseItem
                         {
-                            *write_state = RunState::MobTurn;
+                            *write_state = RunState::PlayerTurn;
                         }
                     }
                     RunState::Equipment => {
@@ -307,11 +437,21 @@ impl<'a> Renderer<'a> {
                         let container_result =
                             container::show_container(self.ecs, self.term, draw_batch);
                         if container_result == container::ContainerResult::Cancel {
-                            *write_state = RunState::MobTurn;
+                            *write_state = RunState::PlayerTurn;
                         } else if container_result == container::ContainerResult::Select {
                             *write_state = RunState::AccessContainer;
                         }
                     }
+                    RunState::LevelUp => {
+                        if let Some(choice) = popup::show_level_up(self.ecs, self.term, draw_batch)
+                        {
+                            level_up(self.ecs, choice);
+                            // Enough XP may have been earned for more than one level.
+                            if !can_level_up(self.ecs) {
+                                *write_state = RunState::MobTurn;
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
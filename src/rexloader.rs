@@ -62,3 +62,8 @@ pub fn load_dungeons() {
     link_resource!(WFC18, "resources/wfc_20x20_6.xp");
     link_resource!(WFC19, "resources/wfc_4x4.xp");
 }
+
+/// Checks if `path` (e.g. "resources/wfc_6x6.xp") was linked by `load_dungeons`.
+pub fn is_loaded(path: &str) -> bool {
+    EMBED.lock().get_resource(path.to_string()).is_some()
+}
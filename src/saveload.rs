@@ -0,0 +1,295 @@
+use super::{
+    components::*,
+    log::Log,
+    map_gen::Map,
+    state::{RunSeed, RunState},
+    systems::mapping::MappingSystem,
+};
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, MarkerAllocator, SerializeComponents, SimpleMarker,
+    SimpleMarkerAllocator,
+};
+use std::convert::Infallible;
+use std::fs::{self, File};
+use std::path::Path;
+
+/*
+ *
+ * saveload.rs
+ * -----------
+ * Writes the whole game (entities, maps, log and state) to a save file and reads it back.
+ *
+ * Based on https://bfnightly.bracketproductions.com/rustbook/chapter_11.html
+ */
+
+const SAVE_FILE: &str = "./savegame.json";
+
+/// Serializes every listed component of the marked entities, one storage at a time.
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<Infallible, SimpleMarker<SerializeMe>>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )
+        .unwrap();
+        )*
+    };
+}
+
+/// Reads back the component storages, in the same order they were written.
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<Infallible, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &$data.0,
+            &mut $data.1,
+            &mut $data.2,
+            &mut $de,
+        )
+        .unwrap();
+        )*
+    };
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+pub fn delete_save() {
+    if does_save_exist() {
+        fs::remove_file(SAVE_FILE).expect("Unable to delete the save file.");
+    }
+}
+
+/// Marks all the living entities, so none of them is left out of the save file.
+fn mark_all(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut markers = ecs.write_storage::<SimpleMarker<SerializeMe>>();
+    let mut allocator = ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>();
+
+    for ent in entities.join() {
+        allocator.mark(ent, &mut markers);
+    }
+}
+
+/// Saves the game. `runstate` is the state the game resumes at when loaded.
+pub fn save_game(ecs: &mut World, maps: &[Map], runstate: RunState) {
+    mark_all(ecs);
+
+    let map = (*ecs.fetch::<Map>()).clone();
+    let log = ecs.fetch::<Log>().messages.clone();
+    let seed = *ecs.fetch::<RunSeed>();
+    let rng = (*ecs.fetch::<RandomNumberGenerator>()).clone();
+    let helper = ecs
+        .create_entity()
+        .with(SerializationHelper {
+            map,
+            maps: maps.to_vec(),
+            log,
+            runstate,
+            seed,
+            rng,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    {
+        let data = (
+            ecs.entities(),
+            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+        );
+
+        let writer = File::create(SAVE_FILE).expect("Unable to create the save file.");
+        let mut serializer = serde_json::Serializer::new(writer);
+        serialize_individually!(
+            ecs,
+            serializer,
+            data,
+            Position,
+            Renderable,
+            Player,
+            Mob,
+            Behavior,
+            StatusEffects,
+            Faction,
+            Name,
+            Description,
+            InventoryCapacity,
+            Fov,
+            Blocker,
+            Health,
+            Attack,
+            BaseStats,
+            Experience,
+            RunStats,
+            Survival,
+            Skills,
+            Energy,
+            SufferDamage,
+            MeleeAttack,
+            MissileAttack,
+            ThrowAttack,
+            Explosive,
+            MeleeWeapon,
+            MissileWeapon,
+            Ammunition,
+            ActiveWeapon,
+            TryReload,
+            Target,
+            Equipable,
+            Equipment,
+            TryEquip,
+            TryUnequip,
+            Item,
+            Armor,
+            Consumable,
+            CollectItem,
+            DropItem,
+            ConsumeItem,
+            Inventory,
+            SelectedItem,
+            SelectedPosition,
+            Remains,
+            Container,
+            Contained,
+            OtherLevelPosition,
+            SerializationHelper
+        );
+    }
+
+    ecs.delete_entity(helper)
+        .expect("FAILED to delete the serialization helper");
+}
+
+/// Loads the game, replacing every entity in the world. Returns the generated maps and the
+/// state to resume at. The save file is deleted afterwards.
+pub fn load_game(ecs: &mut World) -> (Vec<Map>, RunState) {
+    ecs.delete_all();
+    ecs.maintain();
+
+    let data = fs::read_to_string(SAVE_FILE).expect("Unable to read the save file.");
+    let mut de = serde_json::Deserializer::from_str(&data);
+
+    {
+        let mut d = (
+            ecs.entities(),
+            ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+            ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+        );
+        deserialize_individually!(
+            ecs,
+            de,
+            d,
+            Position,
+            Renderable,
+            Player,
+            Mob,
+            Behavior,
+            StatusEffects,
+            Faction,
+            Name,
+            Description,
+            InventoryCapacity,
+            Fov,
+            Blocker,
+            Health,
+            Attack,
+            BaseStats,
+            Experience,
+            RunStats,
+            Survival,
+            Skills,
+            Energy,
+            SufferDamage,
+            MeleeAttack,
+            MissileAttack,
+            ThrowAttack,
+            Explosive,
+            MeleeWeapon,
+            MissileWeapon,
+            Ammunition,
+            ActiveWeapon,
+            TryReload,
+            Target,
+            Equipable,
+            Equipment,
+            TryEquip,
+            TryUnequip,
+            Item,
+            Armor,
+            Consumable,
+            CollectItem,
+            DropItem,
+            ConsumeItem,
+            Inventory,
+            SelectedItem,
+            SelectedPosition,
+            Remains,
+            Container,
+            Contained,
+            OtherLevelPosition,
+            SerializationHelper
+        );
+    }
+
+    let mut maps = Vec::new();
+    let mut runstate = RunState::Running;
+    let mut helper = None;
+    {
+        let entities = ecs.entities();
+        let helpers = ecs.read_storage::<SerializationHelper>();
+        for (ent, h) in (&entities, &helpers).join() {
+            let mut map = h.map.clone();
+            map.entities = vec![None; map.tiles.len()];
+            *ecs.write_resource::<Map>() = map;
+
+            maps = h.maps.clone();
+            for m in maps.iter_mut() {
+                m.entities = vec![None; m.tiles.len()];
+            }
+
+            ecs.write_resource::<Log>().messages = h.log.clone();
+            runstate = h.runstate;
+            *ecs.write_resource::<RunSeed>() = h.seed;
+            *ecs.write_resource::<RandomNumberGenerator>() = h.rng.clone();
+            helper = Some(ent);
+        }
+    }
+
+    if let Some(helper) = helper {
+        ecs.delete_entity(helper)
+            .expect("FAILED to delete the serialization helper");
+    }
+
+    {
+        let entities = ecs.entities();
+        let players = ecs.read_storage::<Player>();
+        let positions = ecs.read_storage::<Position>();
+        let mut fov = ecs.write_storage::<Fov>();
+        let mut player_pos = ecs.write_resource::<Point>();
+        let mut player_ent = ecs.write_resource::<Entity>();
+
+        for (ent, _p, pos) in (&entities, &players, &positions).join() {
+            *player_ent = ent;
+            *player_pos = Point::new(pos.x, pos.y);
+            if let Some(pfov) = fov.get_mut(ent) {
+                pfov.dirty = true;
+            }
+        }
+    }
+
+    let mut mapping = MappingSystem {};
+    mapping.run_now(ecs);
+    ecs.maintain();
+
+    // Permadeath: a save can only be continued once.
+    delete_save();
+
+    (maps, runstate)
+}
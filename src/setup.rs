@@ -0,0 +1,74 @@
+use super::components::*;
+use specs::prelude::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
+
+/*
+ *
+ * setup.rs
+ * --------
+ * Builds an empty world, with every component registered, for a new run (see State::new_game).
+ *
+ */
+
+pub fn new_world() -> World {
+    let mut world = World::new();
+
+    // Register the components (see components.rs).
+    world.register::<Position>();
+    world.register::<Renderable>();
+    world.register::<Player>();
+    world.register::<Mob>();
+    world.register::<Behavior>();
+    world.register::<StatusEffects>();
+    world.register::<Faction>();
+    world.register::<Name>();
+    world.register::<Description>();
+    world.register::<InventoryCapacity>();
+    world.register::<Fov>();
+    world.register::<Blocker>();
+    world.register::<Health>();
+    world.register::<Attack>();
+    world.register::<BaseStats>();
+    world.register::<Experience>();
+    world.register::<RunStats>();
+    world.register::<Survival>();
+    world.register::<Skills>();
+    world.register::<Energy>();
+    world.register::<MyTurn>();
+    world.register::<SufferDamage>();
+    world.register::<MeleeAttack>();
+    world.register::<MissileAttack>();
+    world.register::<ThrowAttack>();
+    world.register::<Projectile>();
+    world.register::<Explosive>();
+    world.register::<MeleeWeapon>();
+    world.register::<MissileWeapon>();
+    world.register::<Ammunition>();
+    world.register::<ActiveWeapon>();
+    world.register::<TryReload>();
+    world.register::<Target>();
+    world.register::<Equipable>();
+    world.register::<Equipment>();
+    world.register::<TryEquip>();
+    world.register::<TryUnequip>();
+    world.register::<Item>();
+    world.register::<Armor>();
+    world.register::<Consumable>();
+    world.register::<CollectItem>();
+    world.register::<DropItem>();
+    world.register::<ConsumeItem>();
+    world.register::<Inventory>();
+    world.register::<SelectedItem>();
+    world.register::<SelectedPosition>();
+    world.register::<Remains>();
+    world.register::<Container>();
+    world.register::<Contained>();
+    world.register::<OtherLevelPosition>();
+    world.register::<SimpleMarker<SerializeMe>>();
+    world.register::<SerializationHelper>();
+
+    // Marker allocator for the entities written to the save file.
+    world.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+    world
+}
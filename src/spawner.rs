@@ -3,12 +3,13 @@ use super::{
     map_gen::{Map, MapType},
     raws::*,
     utils::colors::*,
-    ActiveWeapon, Attack, BaseStats, Contained, Container, Description, Equipment, Fov, Health,
-    Inventory, InventoryCapacity, Mob, Name, Player, Position, Remains, Renderable,
+    ActiveWeapon, Attack, Attributes, BaseStats, Contained, Container, DamageType, Description,
+    Energy, Equipment, Experience, Faction, Fov, Health, Inventory, InventoryCapacity, Mob, Name,
+    Player, Position, Remains, Renderable, RunStats, Skills, Survival, TURN_ENERGY,
 };
 use bracket_lib::prelude::{to_cp437, ColorPair, Point, RandomNumberGenerator};
 use specs::prelude::*;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /*
  *
@@ -85,6 +86,9 @@ pub fn create_player(ecs: &mut World) -> Entity {
             layer: 1,
         })
         .with(Player {})
+        .with(Faction {
+            name: "Player".to_string(),
+        })
         .with(Name {
             name: "Severian".to_string(),
         })
@@ -100,104 +104,88 @@ pub fn create_player(ecs: &mut World) -> Entity {
             health: Health { max_hp: 15, hp: 2 },
             defense: 3,
             attack: Attack {
-                base_damage: "1d3".to_string(),
-                dice_n: 1,
-                dice_faces: 3,
-                dice_bonus: 0,
+                damage: "1d3".parse().unwrap(),
                 range: 0,
+                damage_type: DamageType::Blunt,
+                on_hit: None,
             },
-            god: true,
+            attack_bonus: 0,
+            attributes: Attributes::default(),
+            god: false,
+        })
+        .with(Experience { level: 1, xp: 0 })
+        .with(Skills::default())
+        .with(RunStats::default())
+        .with(Survival::default())
+        .with(Energy {
+            speed: TURN_ENERGY,
+            energy: TURN_ENERGY,
         })
         .with(InventoryCapacity { curr: 0, max: 15 })
         .build()
 }
 
-pub fn equip_player(ecs: &mut World) {
+/// Makes the player the character chosen at character creation: names it, and sets its stats,
+/// equipment and inventory from the background in the raws.
+pub fn create_character(ecs: &mut World, name: &str, background: &str) {
     let raws = &RAWS.lock().unwrap();
-    let melee_weapon = spawn_item("Tantou", None, ecs.create_entity(), raws).unwrap();
-    let missile_weapon = spawn_item("Revolver", None, ecs.create_entity(), raws).unwrap();
-    let armor = spawn_item("Old Leather Armor", None, ecs.create_entity(), raws).unwrap();
-    let pants = spawn_item("Bombacho", None, ecs.create_entity(), raws).unwrap();
-    let ammo = spawn_item(".32 Ammo", None, ecs.create_entity(), raws).unwrap();
-    let cloak = spawn_item("Sagum", None, ecs.create_entity(), raws).unwrap();
-    let gloves = spawn_item("Hide Gloves", None, ecs.create_entity(), raws).unwrap();
-    let boots = spawn_item("Leather Boots", None, ecs.create_entity(), raws).unwrap();
-    let mut equipments = ecs.write_storage::<Equipment>();
-    let player_ent = ecs.fetch::<Entity>();
-
-    equipments
-        .insert(
-            melee_weapon,
-            Equipment {
-                user: *player_ent,
-                equip: melee_weapon,
-            },
-        )
-        .expect("FAILED to equip item.");
-    equipments
-        .insert(
-            armor,
-            Equipment {
-                user: *player_ent,
-                equip: armor,
-            },
-        )
-        .expect("FAILED to equip item.");
-    equipments
-        .insert(
-            pants,
-            Equipment {
-                user: *player_ent,
-                equip: pants,
-            },
-        )
-        .expect("FAILED to equip item.");
-    equipments
-        .insert(
-            cloak,
-            Equipment {
-                user: *player_ent,
-                equip: cloak,
-            },
-        )
-        .expect("FAILED to equip item.");
-    equipments
-        .insert(
-            gloves,
-            Equipment {
-                user: *player_ent,
-                equip: gloves,
-            },
-        )
-        .expect("FAILED to equip item.");
-    equipments
-        .insert(
-            boots,
-            Equipment {
-                user: *player_ent,
-                equip: boots,
-            },
-        )
-        .expect("FAILED to equip item.");
-    equipments
+    let background = get_background(background, raws)
+        .unwrap_or_else(|| panic!("No background named \"{}\" in the raws.", background));
+    let player = *ecs.fetch::<Entity>();
+
+    ecs.write_storage::<Name>()
         .insert(
-            missile_weapon,
-            Equipment {
-                user: *player_ent,
-                equip: missile_weapon,
+            player,
+            Name {
+                name: name.to_string(),
             },
         )
-        .expect("FAILED to equip item.");
+        .expect("FAILED to name the player.");
+    if let Some(stats) = ecs.write_storage::<BaseStats>().get_mut(player) {
+        stats.health = Health {
+            max_hp: background.stats.max_hp,
+            hp: background.stats.max_hp,
+        };
+        stats.defense = background.stats.defense;
+        let attributes = &background.stats.attributes;
+        stats.attributes = Attributes {
+            strength: attributes.strength,
+            agility: attributes.agility,
+            perception: attributes.perception,
+        };
+    }
 
-    let mut active_weapon = ecs.write_storage::<ActiveWeapon>();
-    active_weapon
-        .insert(melee_weapon, ActiveWeapon {})
-        .expect("Insert fail");
+    let mut armed = false;
+    for equip in background.equips.iter() {
+        if let Some(e) = spawn_item(equip, None, ecs.create_entity(), raws) {
+            ecs.write_storage::<Equipment>()
+                .insert(
+                    e,
+                    Equipment {
+                        user: player,
+                        equip: e,
+                    },
+                )
+                .expect("FAILED to equip item.");
+            if !armed && is_weapon(ecs, e) {
+                ecs.write_storage::<ActiveWeapon>()
+                    .insert(e, ActiveWeapon {})
+                    .expect("Insert fail");
+                armed = true;
+            }
+        }
+    }
 
-    let mut inventory = ecs.write_storage::<Inventory>();
-    inventory
-        .insert(ammo, Inventory { owner: *player_ent })
-        .expect("FAILED to insert item in inventory.");
+    for item in background.inventory.iter() {
+        if let Some(e) = spawn_item(item, None, ecs.create_entity(), raws) {
+            ecs.write_storage::<Inventory>()
+                .insert(e, Inventory { owner: player })
+                .expect("FAILED to insert item in inventory.");
+            if let Some(capacity) = ecs.write_storage::<InventoryCapacity>().get_mut(player) {
+                capacity.curr += 1;
+            }
+        }
+    }
 }
 
 fn get_all_tiered_containers(ecs: &World) -> Vec<(Entity, Vec<u8>)> {
@@ -213,12 +201,14 @@ fn get_all_tiered_containers(ecs: &World) -> Vec<(Entity, Vec<u8>)> {
 
 fn get_all_named_mobs(ecs: &World) -> Vec<(Entity, String)> {
     let entities = ecs.entities();
+    let pos = ecs.read_storage::<Position>();
     let mobs = ecs.read_storage::<Mob>();
     let names = ecs.read_storage::<Name>();
 
-    (&mobs, &entities, &names)
+    // Only mobs on the current level: the ones parked on other levels are already equipped.
+    (&pos, &mobs, &entities, &names)
         .join()
-        .map(|(_c, e, n)| (e, n.name.clone()))
+        .map(|(_p, _c, e, n)| (e, n.name.clone()))
         .collect()
 }
 
@@ -276,6 +266,15 @@ fn equip_mobs(ecs: &mut World, raws: &RawMaster, rng: &mut RandomNumberGenerator
                 }
             }
         }
+
+        for item in get_mob_inventory(&mob.1, raws) {
+            if let Some(e) = spawn_item(item.as_str(), None, ecs.create_entity(), raws) {
+                let mut inventory = ecs.write_storage::<Inventory>();
+                inventory
+                    .insert(e, Inventory { owner: mob.0 })
+                    .expect("FAILED to insert item in inventory.");
+            }
+        }
     }
 }
 
@@ -344,7 +343,7 @@ pub fn build_spawn_list(
         return;
     }
     //println!("loc size: {}", loc_size);
-    let mut spawns: HashMap<usize, String> = HashMap::new();
+    let mut spawns: BTreeMap<usize, String> = BTreeMap::new();
     let mut spawn_locs: Vec<usize> = Vec::from(loc);
     let num_mobs = if !is_room {
         i32::min(loc_size, rng.range(level, MAX_MOBS_AREA))
@@ -394,10 +393,14 @@ pub fn spawn_from_list(
 }
 
 pub fn spawn_player(ecs: &mut World, map: &Map) {
+    place_player(ecs, map.spawn_point);
+}
+
+/// Moves the player to `map_pos` on the current map.
+pub fn place_player(ecs: &mut World, map_pos: Position) {
     let player = ecs.fetch::<Entity>();
     let mut pos = ecs.write_storage::<Position>();
     let mut ppos = pos.get_mut(*player).unwrap();
-    let map_pos = map.spawn_point;
     ppos.x = map_pos.x;
     ppos.y = map_pos.y;
 
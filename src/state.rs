@@ -2,19 +2,28 @@ use super::{
     components::*,
     input::*,
     killer::remove_dead_entities,
+    log::Log,
     map_gen::*,
+    morgue::write_morgue,
+    player::{can_level_up, mobs_ready, player_ready, spend_energy},
     raws::*,
     renderer::{reload_colors, render_all},
+    saveload,
+    setup::new_world,
+    spawner::{create_character, create_player, place_player},
     systems::{
         ai::HostileAISystem, consumable::ConsumableSystem, damage::DamageSystem,
-        equipment::EquipmentSystem, fov::FOVSystem, item_collect::ItemCollectSystem,
-        item_drop::ItemDropSystem, mapping::MappingSystem, melee::MeleeSystem,
-        missile::MissileSystem, weapon_reload::WeaponReloadSystem,
+        equipment::EquipmentSystem, explosion::ExplosionSystem, fov::FOVSystem,
+        initiative::InitiativeSystem, item_collect::ItemCollectSystem, item_drop::ItemDropSystem,
+        mapping::MappingSystem, melee::MeleeSystem, missile::MissileSystem, status::StatusSystem,
+        survival::SurvivalSystem, weapon_reload::WeaponReloadSystem,
     },
-    ui::menu::MenuSelection,
-    SHOW_MAP,
+    ui::{character::CharacterDraft, menu::MenuSelection},
+    utils::colors::color,
+    MAP_HEIGHT, MAP_WIDTH, SHOW_MAP,
 };
 use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
 /*
@@ -25,24 +34,35 @@ use specs::prelude::*;
  *
  */
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum RunState {
     Running,
     Waiting,
     Start,
+    CharacterCreation,
     PlayerTurn,
     MobTurn,
     Targeting,
+    AimArea { target: Point, radius: i32 },
     ChooseActionDir,
     Inventory,
     Equipment,
     ItemUse,
     AccessContainer,
+    LevelUp,
+    GameOver,
     Mapgen,
     Menu { menu_selection: MenuSelection },
     NextLevel,
+    PreviousLevel,
+    SaveGame,
+    LoadGame,
 }
 
+/// Seed of the current run, shown on the HUD (in hexadecimal, as passed to `--seed`).
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct RunSeed(pub u64);
+
 pub struct State {
     pub ecs: World,
     pub runstate: RunState,
@@ -52,20 +72,54 @@ pub struct State {
 }
 
 impl State {
-    pub fn new(world: World) -> Self {
-        Self {
-            ecs: world,
+    /// Creates the game state, with a new run from the given seed waiting behind the main menu.
+    pub fn new(seed: u64) -> Self {
+        let mut state = Self {
+            ecs: World::new(),
             runstate: RunState::Start,
             show_map: SHOW_MAP,
             in_menu: true,
-            map_generator: MapGenerator::new(),
-        }
+            map_generator: MapGenerator::new(seed),
+        };
+        state.new_game(seed);
+
+        state
+    }
+
+    /// Sets up a new run, throwing away everything from the previous one: a new world (player and
+    /// first level included), map generator, log and RNG. The run starts at the main menu, and the
+    /// player is only made into a character once it's created (see RunState::Start).
+    pub fn new_game(&mut self, seed: u64) {
+        println!("Run seed: {:x}", seed);
+        self.ecs = new_world();
+        self.map_generator = MapGenerator::new(seed);
+        self.show_map = SHOW_MAP;
+        self.in_menu = true;
+
+        self.ecs.insert(Map::new(80, 60, TileType::Floor, None));
+        self.ecs.insert(RandomNumberGenerator::seeded(seed));
+        self.ecs.insert(RunSeed(seed));
+        self.ecs.insert(Log::new());
+
+        self.generate_new_map(MAP_WIDTH, MAP_HEIGHT);
+        self.ecs.insert(Point::new(0, 0));
+        let player = create_player(&mut self.ecs);
+        self.ecs.insert(player);
+        self.ecs.insert(CharacterDraft::default());
+        self.populate_map();
+
+        self.ecs.insert(RunState::Menu {
+            menu_selection: MenuSelection::NewGame,
+        });
     }
 
     fn run_systems(&mut self) {
         let mut vis = FOVSystem {};
         vis.run_now(&self.ecs);
 
+        let mut initiative = InitiativeSystem {};
+        initiative.run_now(&self.ecs);
+
         let mut hostile_ai = HostileAISystem {};
         hostile_ai.run_now(&self.ecs);
 
@@ -81,6 +135,15 @@ impl State {
         let mut missile = MissileSystem {};
         missile.run_now(&self.ecs);
 
+        let mut explosion = ExplosionSystem {};
+        explosion.run_now(&self.ecs);
+
+        let mut status = StatusSystem {};
+        status.run_now(&self.ecs);
+
+        let mut survival = SurvivalSystem {};
+        survival.run_now(&self.ecs);
+
         let mut damage = DamageSystem {};
         damage.run_now(&self.ecs);
 
@@ -151,6 +214,96 @@ impl State {
         self.map_generator.spawn_entities(&mut self.ecs, idx);
     }
 
+    /// Moves the player to the level at `idx`, generating it if it's a new one. Everything left
+    /// on the current level is parked there and brought back when the player returns.
+    pub fn change_level(&mut self, idx: usize) {
+        let curr = self.ecs.fetch::<Map>().level;
+        self.park_entities(curr);
+        self.map_generator.maps[curr] = (*self.ecs.fetch::<Map>()).clone();
+
+        if idx < self.map_generator.maps.len() {
+            self.set_curr_map(idx);
+            self.unpark_entities(idx);
+            // Going up, the player arrives on the ">" of that level; going down, on its "<".
+            let map = &self.map_generator.maps[idx];
+            let arrival = if idx < curr {
+                map.exit_point
+            } else {
+                map.spawn_point
+            };
+            place_player(&mut self.ecs, arrival);
+        } else {
+            self.generate_new_map(MAP_WIDTH, MAP_HEIGHT);
+            self.populate_map();
+        }
+
+        let mut log = self.ecs.fetch_mut::<Log>();
+        let verb = if idx < curr { "climb up" } else { "go down" };
+        log.add(
+            format!("You {} to level {}.", verb, idx + 1),
+            color("BrightWhite", 1.0),
+        );
+    }
+
+    /// Takes every entity on the map (except the player) out of it, remembering their position
+    /// and level in an OtherLevelPosition.
+    fn park_entities(&mut self, level: usize) {
+        let entities = self.ecs.entities();
+        let player = self.ecs.fetch::<Entity>();
+        let mut positions = self.ecs.write_storage::<Position>();
+        let mut parked = self.ecs.write_storage::<OtherLevelPosition>();
+        let mut targets = self.ecs.write_storage::<Target>();
+
+        let to_park: Vec<(Entity, Position)> = (&entities, &positions)
+            .join()
+            .filter(|(ent, _pos)| *ent != *player)
+            .map(|(ent, pos)| (ent, *pos))
+            .collect();
+
+        for (ent, pos) in to_park {
+            positions.remove(ent);
+            parked
+                .insert(ent, OtherLevelPosition { pos, level })
+                .expect("FAILED to park entity.");
+        }
+
+        targets.clear();
+    }
+
+    /// Puts back on the map the entities parked on the given level.
+    fn unpark_entities(&mut self, level: usize) {
+        let entities = self.ecs.entities();
+        let mut positions = self.ecs.write_storage::<Position>();
+        let mut parked = self.ecs.write_storage::<OtherLevelPosition>();
+
+        let to_unpark: Vec<(Entity, Position)> = (&entities, &parked)
+            .join()
+            .filter(|(_ent, other)| other.level == level)
+            .map(|(ent, other)| (ent, other.pos))
+            .collect();
+
+        for (ent, pos) in to_unpark {
+            parked.remove(ent);
+            positions
+                .insert(ent, pos)
+                .expect("FAILED to unpark entity.");
+        }
+    }
+
+    /// Ends the run once the player dies: hides the map behind the death screen and writes the
+    /// morgue file.
+    fn game_over(&mut self) -> RunState {
+        self.in_menu = true;
+        let red = color("BrightRed", 1.0);
+        self.ecs.fetch_mut::<Log>().add("You died...", red);
+        match write_morgue(&self.ecs) {
+            Ok(path) => println!("Morgue file written to {}.", path),
+            Err(e) => eprintln!("Unable to write the morgue file: {}", e),
+        }
+
+        RunState::GameOver
+    }
+
     pub fn set_curr_map(&mut self, idx: usize) {
         let mut curr_map = self.ecs.write_resource::<Map>();
         *curr_map = self.map_generator.get_map(idx);
@@ -175,6 +328,17 @@ impl GameState for State {
         match curr_state {
             RunState::Menu { .. } => {}
             RunState::Start => {
+                // The journey begins, with the character the player created. A seed typed in
+                // replaces the one the run was set up with.
+                let draft = CharacterDraft::clone(&self.ecs.fetch());
+                match draft.seed() {
+                    Some(seed) if seed != self.ecs.fetch::<RunSeed>().0 => self.new_game(seed),
+                    _ => {}
+                }
+                let background = RAWS.lock().unwrap().raws.backgrounds[draft.background]
+                    .name
+                    .clone();
+                create_character(&mut self.ecs, &draft.name, &background);
                 self.in_menu = false;
                 if self.show_map {
                     curr_state = RunState::Mapgen;
@@ -190,16 +354,31 @@ impl GameState for State {
                 curr_state = player_input(self, term);
             }
             RunState::PlayerTurn => {
+                spend_energy(&self.ecs);
                 self.run_systems();
-                curr_state = RunState::MobTurn;
+                curr_state = if can_level_up(&self.ecs) {
+                    RunState::LevelUp
+                } else {
+                    RunState::MobTurn
+                };
             }
             RunState::MobTurn => {
+                // The mobs act, as many times as their energy allows, until the player can.
                 self.run_systems();
-                curr_state = RunState::Waiting;
+                curr_state = if can_level_up(&self.ecs) {
+                    RunState::LevelUp
+                } else if !mobs_ready(&self.ecs) && player_ready(&self.ecs) {
+                    RunState::Waiting
+                } else {
+                    RunState::MobTurn
+                };
             }
             RunState::Targeting => {
                 curr_state = targeting_input(self, term);
             }
+            RunState::AimArea { target, radius } => {
+                curr_state = aim_area_input(self, term, target, radius);
+            }
             RunState::ChooseActionDir => {
                 curr_state = action_dir_input(self, term);
             }
@@ -217,6 +396,17 @@ impl GameState for State {
                 self.run_collect_system();
                 curr_state = RunState::AccessContainer;
             }
+            RunState::LevelUp | RunState::CharacterCreation => {
+                // Changes state on rendering, once the player chooses.
+            }
+            RunState::GameOver => {
+                if let Some(VirtualKeyCode::Return) = term.key {
+                    self.new_game(RandomNumberGenerator::new().next_u64());
+                    curr_state = RunState::Menu {
+                        menu_selection: MenuSelection::NewGame,
+                    };
+                }
+            }
             RunState::Mapgen => match term.key {
                 None => {
                     //self.run_systems();
@@ -228,7 +418,7 @@ impl GameState for State {
                                 .delete_entity(ent)
                                 .expect("FAILED to delete entity");
                         }
-                        self.generate_new_map(80, 60);
+                        self.generate_new_map(MAP_WIDTH, MAP_HEIGHT);
                         self.populate_map();
                     }
                     if let VirtualKeyCode::Return = key {
@@ -238,15 +428,32 @@ impl GameState for State {
                 }
             },
             RunState::NextLevel => {
-                for ent in self.entities_to_delete() {
-                    self.ecs
-                        .delete_entity(ent)
-                        .expect("FAILED to delete entity");
+                let curr = self.ecs.fetch::<Map>().level;
+                self.change_level(curr + 1);
+                curr_state = RunState::Running;
+            }
+            RunState::PreviousLevel => {
+                let curr = self.ecs.fetch::<Map>().level;
+                if curr > 0 {
+                    self.change_level(curr - 1);
                 }
-                self.generate_new_map(80, 60);
-                self.populate_map();
                 curr_state = RunState::Running;
             }
+            RunState::SaveGame => {
+                saveload::save_game(&mut self.ecs, &self.map_generator.maps, RunState::Running);
+                // The saved run is now only reached through "Continue"; "New Journey" starts afresh.
+                self.new_game(RandomNumberGenerator::new().next_u64());
+                curr_state = RunState::Menu {
+                    menu_selection: MenuSelection::LoadGame,
+                };
+            }
+            RunState::LoadGame => {
+                let (maps, runstate) = saveload::load_game(&mut self.ecs);
+                self.map_generator.maps = maps;
+                self.map_generator.seed = self.ecs.fetch::<RunSeed>().0;
+                self.in_menu = false;
+                curr_state = runstate;
+            }
         }
 
         // F3 to enable/disable post-processing effects.
@@ -271,12 +478,15 @@ impl GameState for State {
             }
         }
 
+        if remove_dead_entities(&mut self.ecs) && !self.in_menu {
+            curr_state = self.game_over();
+        }
+
         {
             let mut write_state = self.ecs.write_resource::<RunState>();
             *write_state = curr_state;
         }
 
-        remove_dead_entities(&mut self.ecs);
         render_all(&self.ecs, term, curr_state, self.show_map, self.in_menu);
     }
 }
@@ -1,5 +1,11 @@
-use crate::components::{Fov, MeleeAttack, Mob, Position};
+use super::initiative::{melee_cost, missile_cost, move_cost, ACTION_COST, RELOAD_COST};
+use crate::components::{
+    AIState, ActiveWeapon, Ammunition, BaseStats, Behavior, Energy, Equipment, Explosive, Faction,
+    Fov, Health, Inventory, MeleeAttack, MeleeWeapon, MissileAttack, MissileWeapon, Mob, MyTurn,
+    Name, Position, Reaction, StatusEffects, StatusType, ThrowAttack, TryReload,
+};
 use crate::map_gen::Map;
+use crate::raws::{faction_reaction, RAWS};
 use crate::state::RunState;
 use bracket_lib::prelude::*;
 use specs::prelude::*;
@@ -8,58 +14,374 @@ use specs::prelude::*;
  *
  * ai.rs
  * -----
- * Manages the mobs' AI.
+ * Manages the mobs' AI. Each mob has a Behavior state that changes according to the hostile
+ * entities (per the faction table in the raws) it sees and how hurt it is, and decides what the
+ * mob does on its turn. Only mobs with enough energy act, spending as much as their action costs.
  *
  */
 
+const WAKE_DISTANCE: f32 = 4.0; // Sleeping mobs notice a visible enemy this close.
+const KEEP_AWAY: f32 = 0.5; // Of its weapon's range, the distance a loaded ranged mob keeps.
+
 pub struct HostileAISystem {}
 
 impl<'a> System<'a> for HostileAISystem {
     type SystemData = (
         ReadStorage<'a, Mob>,
         ReadExpect<'a, Point>,
-        ReadExpect<'a, Entity>,
         WriteExpect<'a, Map>,
         WriteStorage<'a, Fov>,
         WriteStorage<'a, Position>,
         ReadExpect<'a, RunState>,
         Entities<'a>,
         WriteStorage<'a, MeleeAttack>,
+        WriteStorage<'a, MissileAttack>,
+        WriteStorage<'a, TryReload>,
+        ReadStorage<'a, Equipment>,
+        ReadStorage<'a, ActiveWeapon>,
+        ReadStorage<'a, MissileWeapon>,
+        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, Ammunition>,
+        WriteStorage<'a, Behavior>,
+        ReadStorage<'a, BaseStats>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, StatusEffects>,
+        ReadStorage<'a, Explosive>,
+        WriteStorage<'a, ThrowAttack>,
+        ReadStorage<'a, MyTurn>,
+        WriteStorage<'a, Energy>,
+        ReadStorage<'a, MeleeWeapon>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mob, pt, player, mut map, mut fov, mut pos, runstate, entities, mut melee_attack) =
-            data;
-        let ppos = *pt;
+        let (
+            mob,
+            pt,
+            mut map,
+            mut fov,
+            mut pos,
+            runstate,
+            entities,
+            mut melee_attack,
+            mut missile_attack,
+            mut try_reload,
+            equipment,
+            active_wpn,
+            missile_wpns,
+            inventory,
+            ammunition,
+            mut behaviors,
+            base_stats,
+            names,
+            mut rng,
+            factions,
+            status_effects,
+            explosives,
+            mut throw_attack,
+            my_turn,
+            mut energies,
+            melee_wpns,
+        ) = data;
         let map = &mut *map;
 
         if *runstate != RunState::MobTurn {
             return;
         }
+        let raws = &RAWS.lock().unwrap();
+
+        // Everything that can be fought. Positions are updated as the mobs move.
+        let mut creatures: Vec<(Entity, Point, String)> = (&entities, &factions, &pos, &base_stats)
+            .join()
+            .filter(|(_, _, _, stats)| stats.health.hp > 0)
+            .map(|(e, f, p, _)| (e, Point::new(p.x, p.y), f.name.clone()))
+            .collect();
+
+        // The player isn't a Blocker, but mobs must not walk into it either.
+        let pidx = map.idx_pt(*pt);
+        let player_tile_block = map.tiles[pidx].block;
+        map.tiles[pidx].block = true;
+
+        for (_mob, fov, pos, behavior, stats, name, faction, ent) in (
+            &mob,
+            &mut fov,
+            &mut pos,
+            &mut behaviors,
+            &base_stats,
+            &names,
+            &factions,
+            &entities,
+        )
+            .join()
+        {
+            if !my_turn.contains(ent) || stats.health.hp <= 0 {
+                continue;
+            }
+            // Stunned mobs lose their turn.
+            if matches!(status_effects.get(ent), Some(s) if s.has(StatusType::Stunned)) {
+                if let Some(energy) = energies.get_mut(ent) {
+                    energy.energy -= ACTION_COST;
+                }
+                continue;
+            }
+            let mpos = Point::new(pos.x, pos.y);
 
-        for (_mob, mut fov, mut pos, ent) in (&mob, &mut fov, &mut pos, &entities).join() {
-            let d = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), ppos);
-            if d < 1.5 {
-                melee_attack
-                    .insert(ent, MeleeAttack { target: *player })
-                    .expect("Melee attack insertion failed");
+            // The closest enemy in sight.
+            let target = creatures
+                .iter()
+                .filter(|(e, p, f)| {
+                    *e != ent
+                        && fov.visible_pos.contains(p)
+                        && faction_reaction(&faction.name, f, raws) == Reaction::Hostile
+                })
+                .map(|(e, p, _)| (*e, *p, DistanceAlg::Pythagoras.distance2d(mpos, *p)))
+                .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+
+            let next = next_state(behavior, target.map(|t| t.2), &stats.health);
+            set_state(behavior, next, &name.name);
+            if let Some((_, tpos, _)) = target {
+                behavior.last_seen = Some(tpos);
             }
-            // https://github.com/thebracket/bracket-lib/blob/master/bracket-pathfinding/examples/astar/main.rs
-            else if fov.visible_pos.contains(&ppos) {
-                // TODO: if has missile weapon w/ ammo, first try missile attack while fleeing; else chase player.
-                let mob_location = map.idx(pos.x, pos.y);
-                let player_location = map.idx(ppos.x, ppos.y);
-                let a_star = a_star_search(mob_location, player_location, map);
-
-                if a_star.success && a_star.steps.len() > 1 {
-                    // Previous position is now unblocked.
-                    map.clear_blocker(pos.x, pos.y);
-                    pos.x = a_star.steps[1] as i32 % map.width;
-                    pos.y = a_star.steps[1] as i32 / map.width;
-                    map.add_blocker(pos.x, pos.y);
-                    fov.dirty = true;
+
+            match (behavior.state, target) {
+                (AIState::Investigate, _) => {
+                    let dest = behavior.last_seen.unwrap_or(mpos);
+                    if dest == mpos || !step_towards(map, pos, fov, dest) {
+                        // Nobody there: give up.
+                        behavior.last_seen = None;
+                        let rest = behavior.rest;
+                        set_state(behavior, rest, &name.name);
+                    }
                 }
+                (AIState::Wander, _) => wander(map, pos, fov, &mut rng),
+                (AIState::Flee, Some((tent, tpos, d))) => {
+                    // Cornered mobs fight back.
+                    if !step_away(map, pos, fov, tpos, false) && d < 1.5 {
+                        melee_attack
+                            .insert(ent, MeleeAttack { target: tent })
+                            .expect("Melee attack insertion failed");
+                    }
+                }
+                (AIState::Chase, Some((tent, tpos, d))) => {
+                    // Mobs with a ranged weapon shoot while they have ammo, backing off while the
+                    // target is closer than they like and only closing in to get it in range or in
+                    // their line of fire. They reload when it's empty and only melee when out of
+                    // ammo (or cornered).
+                    let wpn = (&entities, &equipment, &active_wpn, &missile_wpns)
+                        .join()
+                        .find(|(_, equip, _, _)| equip.user == ent)
+                        .map(|(w, _, _, missile)| (w, missile));
+
+                    let mut acted = false;
+                    if let Some((w, missile)) = wpn {
+                        if missile.ammo.ammo > 0 {
+                            let range = missile.stats.range as f32;
+                            let keep_away = match explosives.get(w) {
+                                Some(explosive) => {
+                                    f32::max(range * KEEP_AWAY, explosive.radius as f32 + 1.0)
+                                }
+                                None => range * KEEP_AWAY,
+                            };
+                            acted = d < keep_away && step_away(map, pos, fov, tpos, true);
+                            if !acted && d <= range {
+                                match explosives.get(w) {
+                                    // Explosives fly over blockers, but are never thrown close
+                                    // enough to catch the thrower in the blast.
+                                    Some(explosive) => {
+                                        if d > explosive.radius as f32
+                                            && map.cover_between(mpos, tpos).is_some()
+                                        {
+                                            throw_attack
+                                                .insert(ent, ThrowAttack { target: tpos })
+                                                .expect("Throw attack insertion failed");
+                                            acted = true;
+                                        }
+                                    }
+                                    None => {
+                                        if map.line_of_fire(mpos, tpos) {
+                                            missile_attack
+                                                .insert(ent, MissileAttack { target: tent })
+                                                .expect("Missile attack insertion failed");
+                                            acted = true;
+                                        }
+                                    }
+                                }
+                            }
+                        } else if has_ammo(ent, &missile.ammo, &inventory, &ammunition) {
+                            try_reload
+                                .insert(ent, TryReload { weapon: w })
+                                .expect("Reload insertion failed");
+                            acted = true;
+                        }
+                    }
+
+                    if !acted {
+                        if d < 1.5 {
+                            melee_attack
+                                .insert(ent, MeleeAttack { target: tent })
+                                .expect("Melee attack insertion failed");
+                        } else {
+                            step_towards(map, pos, fov, tpos);
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            let cost = if melee_attack.contains(ent) {
+                melee_cost(
+                    (&equipment, &active_wpn, &melee_wpns)
+                        .join()
+                        .find(|(equip, _, _)| equip.user == ent)
+                        .map(|(_, _, melee)| melee.class),
+                )
+            } else if missile_attack.contains(ent) || throw_attack.contains(ent) {
+                (&equipment, &active_wpn, &missile_wpns)
+                    .join()
+                    .find(|(equip, _, _)| equip.user == ent)
+                    .map_or(ACTION_COST, |(_, _, missile)| missile_cost(missile.class))
+            } else if try_reload.contains(ent) {
+                RELOAD_COST
+            } else {
+                move_cost(map, *pos)
+            };
+            if let Some(energy) = energies.get_mut(ent) {
+                energy.energy -= cost;
+            }
+
+            if let Some(c) = creatures.iter_mut().find(|c| c.0 == ent) {
+                c.1 = Point::new(pos.x, pos.y);
             }
         }
+
+        map.tiles[pidx].block = player_tile_block;
+    }
+}
+
+/// Picks the mob's state for this turn, given the distance to the closest enemy it sees.
+/// Sleeping mobs only wake up when hurt or when an enemy gets close; awake ones chase the enemy,
+/// or flee from it when badly hurt.
+fn next_state(behavior: &Behavior, enemy_dist: Option<f32>, health: &Health) -> AIState {
+    let hurt = health.hp < health.max_hp;
+    let close = matches!(enemy_dist, Some(d) if d <= WAKE_DISTANCE);
+    if behavior.state == AIState::Sleep && !hurt && !close {
+        return AIState::Sleep;
     }
+
+    if enemy_dist.is_some() {
+        if health.hp * 100 <= behavior.flee_hp * health.max_hp {
+            AIState::Flee
+        } else {
+            AIState::Chase
+        }
+    } else {
+        match behavior.state {
+            AIState::Chase => AIState::Investigate,
+            AIState::Flee | AIState::Sleep => behavior.rest,
+            state => state,
+        }
+    }
+}
+
+fn set_state(behavior: &mut Behavior, state: AIState, name: &str) {
+    if behavior.state != state {
+        if cfg!(debug_assertions) {
+            println!("{}: {} -> {}", name, behavior.state, state);
+        }
+        behavior.state = state;
+    }
+}
+
+/// Checks if the mob carries ammunition that fits its weapon.
+fn has_ammo(
+    ent: Entity,
+    wpn_ammo: &Ammunition,
+    inventory: &ReadStorage<Inventory>,
+    ammunition: &ReadStorage<Ammunition>,
+) -> bool {
+    (inventory, ammunition)
+        .join()
+        .any(|(inv, amm)| inv.owner == ent && amm.ammo_type == wpn_ammo.ammo_type && amm.ammo > 0)
+}
+
+/// Moves the mob one step along the shortest path to the target. Returns false if there's no path.
+// https://github.com/thebracket/bracket-lib/blob/master/bracket-pathfinding/examples/astar/main.rs
+fn step_towards(map: &mut Map, pos: &mut Position, fov: &mut Fov, target: Point) -> bool {
+    let mob_location = map.idx(pos.x, pos.y);
+    let target_location = map.idx(target.x, target.y);
+
+    // The target itself may be a blocker, which A* would never reach.
+    let target_block = map.tiles[target_location].block;
+    map.tiles[target_location].block = false;
+    let a_star = a_star_search(mob_location, target_location, map);
+    map.tiles[target_location].block = target_block;
+
+    if a_star.success && a_star.steps.len() > 1 && !map.tiles[a_star.steps[1]].block {
+        let next = map.idx_pos(a_star.steps[1]);
+        move_mob(map, pos, fov, next);
+        return true;
+    }
+
+    false
+}
+
+/// Takes a step in a random direction, if it's free.
+fn wander(map: &mut Map, pos: &mut Position, fov: &mut Fov, rng: &mut RandomNumberGenerator) {
+    let next = Position::new(pos.x + rng.range(-1, 2), pos.y + rng.range(-1, 2));
+    if next != *pos && map.in_map_bounds(next) && !map.tiles[map.idx(next.x, next.y)].block {
+        move_mob(map, pos, fov, next);
+    }
+}
+
+/// Moves the mob to the free neighbor farthest from the target, only among those with a line of
+/// fire to it if `keep_aim`. Returns false if it's cornered.
+fn step_away(
+    map: &mut Map,
+    pos: &mut Position,
+    fov: &mut Fov,
+    target: Point,
+    keep_aim: bool,
+) -> bool {
+    let curr = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), target);
+    let mut best: Option<(Position, f32)> = None;
+    // The mob's own tile would be in the way when backing straight off.
+    let idx = map.idx(pos.x, pos.y);
+    let block = map.tiles[idx].block;
+    map.tiles[idx].block = false;
+
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let next = Position::new(pos.x + dx, pos.y + dy);
+            if !map.in_map_bounds(next) || map.tiles[map.idx(next.x, next.y)].block {
+                continue;
+            }
+            let next_pt = Point::new(next.x, next.y);
+            if keep_aim && !map.line_of_fire(next_pt, target) {
+                continue;
+            }
+            let d = DistanceAlg::Pythagoras.distance2d(next_pt, target);
+            if d > best.map_or(curr, |b| b.1) {
+                best = Some((next, d));
+            }
+        }
+    }
+    map.tiles[idx].block = block;
+
+    match best {
+        Some((next, _)) => {
+            move_mob(map, pos, fov, next);
+            true
+        }
+        None => false,
+    }
+}
+
+fn move_mob(map: &mut Map, pos: &mut Position, fov: &mut Fov, next: Position) {
+    // Previous position is now unblocked.
+    map.clear_blocker(pos.x, pos.y);
+    pos.x = next.x;
+    pos.y = next.y;
+    map.add_blocker(pos.x, pos.y);
+    fov.dirty = true;
 }
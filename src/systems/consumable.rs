@@ -1,4 +1,7 @@
-use crate::components::{BaseStats, Consumable, ConsumeItem, Inventory, InventoryCapacity, Name};
+use crate::components::{
+    BaseStats, Consumable, ConsumeItem, Inventory, InventoryCapacity, Name, StatusEffects,
+    Survival, SURVIVAL_MAX,
+};
 use crate::log::Log;
 use crate::utils::colors::*;
 use specs::prelude::*;
@@ -23,6 +26,8 @@ impl<'a> System<'a> for ConsumableSystem {
         WriteStorage<'a, ConsumeItem>,
         WriteStorage<'a, Inventory>,
         WriteStorage<'a, BaseStats>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, Survival>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -35,6 +40,8 @@ impl<'a> System<'a> for ConsumableSystem {
             mut to_consume,
             mut inventory,
             mut stats,
+            mut status_effects,
+            mut survival,
         ) = data;
 
         let mut inventory_cap = capacity.get_mut(*player).unwrap();
@@ -50,14 +57,25 @@ impl<'a> System<'a> for ConsumableSystem {
                     target_stats.health.hp + item.heal,
                 );
                 if c.target == *player {
-                    log.add(
-                        format!(
-                            "You consume the {}, healing {} hp.",
-                            name.get(c.item).unwrap().name,
-                            item.heal
-                        ),
-                        white,
-                    );
+                    let item_name = &name.get(c.item).unwrap().name;
+                    if item.heal > 0 {
+                        log.add(
+                            format!("You consume the {}, healing {} hp.", item_name, item.heal),
+                            white,
+                        );
+                    } else {
+                        log.add(format!("You consume the {}.", item_name), white);
+                    }
+                }
+                if let Some(meters) = survival.get_mut(c.target) {
+                    meters.hunger = i32::min(SURVIVAL_MAX, meters.hunger + item.food);
+                    meters.thirst = i32::min(SURVIVAL_MAX, meters.thirst + item.drink);
+                }
+                for &(status, turns) in item.statuses.iter() {
+                    StatusEffects::add_status(&mut status_effects, c.target, status, turns, false);
+                    if c.target == *player {
+                        log.add(format!("You are {}!", status), white);
+                    }
                 }
                 has_consumed = true;
             }
@@ -1,16 +1,25 @@
 //use bracket_lib::prelude::*;
-use crate::components::{BaseStats, Position, SufferDamage};
+use crate::common::is_seen_by;
+use crate::components::{
+    Armor, BaseStats, DamageType, Equipment, Experience, Fov, Mob, Name, Position, RunStats,
+    SufferDamage, Survival,
+};
+use crate::log::Log;
 use crate::map_gen::Map;
+use crate::utils::colors::*;
 use specs::prelude::*;
 
 /*
  *
  * damage.rs
  * ---------
- * Manages everything regarding damage.
+ * Manages everything regarding damage, including the XP the player earns from kills and what
+ * ends up killing the player.
  *
  */
 
+const XP_PER_DIFFICULTY: i32 = 5;
+
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
@@ -21,24 +30,130 @@ impl<'a> System<'a> for DamageSystem {
         WriteStorage<'a, BaseStats>,
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
+        ReadStorage<'a, Equipment>,
+        ReadStorage<'a, Armor>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Fov>,
+        ReadStorage<'a, Mob>,
+        WriteStorage<'a, Experience>,
+        WriteStorage<'a, RunStats>,
+        WriteStorage<'a, Survival>,
+        WriteExpect<'a, Log>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut damage, entities, _player, mut stats, mut map, position) = data;
+        let (
+            mut damage,
+            entities,
+            player,
+            mut stats,
+            mut map,
+            position,
+            equipment,
+            armor,
+            names,
+            fov,
+            mobs,
+            mut experience,
+            mut run_stats,
+            mut survival,
+            mut log,
+        ) = data;
+        let white = color("BrightWhite", 1.0);
+
+        for (damage, ent, victim_stats, pos) in (&damage, &entities, &mut stats, &position).join() {
+            let seen = ent == *player || is_seen_by(ent, *player, &position, &fov);
+            let was_alive = victim_stats.health.hp > 0;
+            let mut lethal = None; // The killing blow.
+            for (amount, dtype, from_player, source) in damage.amount.iter() {
+                let (amount, dtype) = (*amount, *dtype);
+                let mut dmg = amount;
+                // Armor only matters for attacks that got through, and not against starving.
+                let res = if dtype.is_deprivation() {
+                    0
+                } else {
+                    resistance(ent, dtype, &equipment, &armor)
+                };
+                if amount > 0 && res != 0 {
+                    dmg = i32::max(0, amount - res);
+                    if seen {
+                        let msg = match (ent == *player, res > 0) {
+                            (true, true) => {
+                                format!("Your armor absorbs {} {} damage.", amount - dmg, dtype)
+                            }
+                            (true, false) => format!("You take {} extra {} damage.", -res, dtype),
+                            (false, true) => format!(
+                                "{}'s armor absorbs {} {} damage.",
+                                names.get(ent).map_or("It", |n| &n.name),
+                                amount - dmg,
+                                dtype
+                            ),
+                            (false, false) => format!(
+                                "{} takes {} extra {} damage.",
+                                names.get(ent).map_or("It", |n| &n.name),
+                                -res,
+                                dtype
+                            ),
+                        };
+                        log.add(msg, white);
+                    }
+                }
 
-        for (damage, _ent, victim_stats, pos) in (&damage, &entities, &mut stats, &position).join()
-        {
-            if !victim_stats.god {
-                for dmg in damage.amount.iter() {
+                if !victim_stats.god {
                     //println!("{}", victim_stats.health.hp);
-                    victim_stats.health.hp -= dmg.0;
+                    victim_stats.health.hp -= dmg;
+                }
+                if was_alive && victim_stats.health.hp <= 0 && lethal.is_none() {
+                    lethal = Some((*from_player, source, dtype));
+                }
+            }
+            // Getting hurt wakes the player up.
+            if damage.amount.iter().any(|d| d.0 > 0) {
+                if let Some(meters) = survival.get_mut(ent) {
+                    meters.resting = false;
                 }
             }
             // Victim is dead, so clear blocker.
             if victim_stats.health.hp <= 0 {
                 map.clear_blocker(pos.x, pos.y);
             }
+            match lethal {
+                Some((_, source, dtype)) if ent == *player => {
+                    if let Some(stats) = run_stats.get_mut(*player) {
+                        stats.cause_of_death = Some(if dtype.is_deprivation() {
+                            format!("Died of {}.", source)
+                        } else {
+                            format!("Killed by {} ({} damage).", source, dtype)
+                        });
+                    }
+                }
+                Some((true, _, _)) => {
+                    if let (Some(mob), Some(exp)) = (mobs.get(ent), experience.get_mut(*player)) {
+                        let xp = mob.difficulty * XP_PER_DIFFICULTY;
+                        exp.xp += xp;
+                        log.add(format!("You gain {} XP.", xp), white);
+                    }
+                    if let (Some(mob), Some(stats)) = (mobs.get(ent), run_stats.get_mut(*player)) {
+                        *stats.kills.entry(mob.mob_type.to_string()).or_insert(0) += 1;
+                    }
+                }
+                _ => {}
+            }
         }
         damage.clear();
     }
 }
+
+/// Total resistance of the armor `ent` has equipped against `dtype` (negative for a weakness).
+pub fn resistance(
+    ent: Entity,
+    dtype: DamageType,
+    equipment: &ReadStorage<Equipment>,
+    armor: &ReadStorage<Armor>,
+) -> i32 {
+    (equipment, armor)
+        .join()
+        .filter(|(equip, _)| equip.user == ent)
+        .map(|(_, armor)| *armor.resistances.get(&dtype).unwrap_or(&0))
+        .sum()
+}
@@ -0,0 +1,195 @@
+use crate::components::{
+    ActiveWeapon, BaseStats, Equipment, Explosive, Fov, MissileWeapon, Name, Position,
+    StatusEffects, StatusType, SufferDamage, ThrowAttack,
+};
+use crate::log::Log;
+use crate::map_gen::{Map, TileType};
+use crate::utils::colors::*;
+use bracket_lib::prelude::{line2d_bresenham, BaseMap, DistanceAlg, Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/*
+ *
+ * explosion.rs
+ * ------------
+ * Resposible for thrown explosives (grenades): they fly towards the aimed tile and blow up where
+ * they land, hurting everything in the blast radius, the thrower included.
+ *
+ */
+
+const BURNING_TURNS: i32 = 3; // For those standing on ignited vegetation.
+
+pub struct ExplosionSystem {}
+
+impl<'a> System<'a> for ExplosionSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteStorage<'a, ThrowAttack>,
+        WriteStorage<'a, MissileWeapon>,
+        ReadStorage<'a, Explosive>,
+        ReadStorage<'a, Equipment>,
+        ReadStorage<'a, ActiveWeapon>,
+        ReadStorage<'a, BaseStats>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Fov>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, StatusEffects>,
+        WriteExpect<'a, Map>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, Log>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            mut throw_attack,
+            mut missile_wpns,
+            explosives,
+            equipment,
+            active_wpn,
+            base_stats,
+            positions,
+            names,
+            mut fov,
+            mut do_damage,
+            mut status_effects,
+            mut map,
+            mut rng,
+            mut log,
+        ) = data;
+        let white = color("BrightWhite", 1.0);
+        let mut doors_opened = false;
+
+        for (thrower, throw) in (&entities, &throw_attack).join() {
+            let origin = match positions.get(thrower) {
+                Some(pos) => *pos,
+                None => continue,
+            };
+            let wpn = (
+                &mut missile_wpns,
+                &explosives,
+                &equipment,
+                &active_wpn,
+                &names,
+            )
+                .join()
+                .find(|(missile, _, equip, _, _)| equip.user == thrower && missile.ammo.ammo > 0);
+            let (missile, explosive, name_wpn) = match wpn {
+                Some((missile, explosive, _, _, name)) => (missile, explosive, name),
+                None => continue,
+            };
+            missile.ammo.ammo -= 1;
+            let stats = &missile.stats;
+            let radius = explosive.radius;
+            let center = landing_point(&map, origin, throw.target, stats.range);
+
+            let player_sees = |pt: Point, fov: &WriteStorage<Fov>| matches!(fov.get(*player), Some(f) if f.visible_pos.contains(&pt));
+            if thrower == *player || player_sees(center, &fov) {
+                log.add(format!("The {} explodes!", &name_wpn.name), white);
+            }
+
+            // Tiles reached by the blast, with their distance to the center. Walls shelter what's
+            // behind them.
+            let mut blast: Vec<(Point, f32)> = Vec::new();
+            let mut ignited: Vec<Point> = Vec::new();
+            for y in center.y - radius..=center.y + radius {
+                for x in center.x - radius..=center.x + radius {
+                    let pt = Point::new(x, y);
+                    let dist = DistanceAlg::Pythagoras.distance2d(center, pt);
+                    if !map.in_map_bounds(pt)
+                        || dist > radius as f32
+                        || map.cover_between(center, pt).is_none()
+                    {
+                        continue;
+                    }
+                    blast.push((pt, dist));
+
+                    let idx = map.idx_pt(pt);
+                    if map.tiles[idx].ttype == TileType::ClosedDoor {
+                        map.paint_tile(idx, TileType::OpenDoor);
+                        map.reveal(idx);
+                        doors_opened = true;
+                    } else if map.is_foliage(idx) {
+                        map.paint_tile(idx, TileType::Ashes);
+                        map.reveal(idx);
+                        ignited.push(pt);
+                    }
+                }
+            }
+
+            // Damage falls off from the center.
+            let roll = stats.damage.roll(&mut rng);
+            for (victim, pos, victim_stats, name) in
+                (&entities, &positions, &base_stats, &names).join()
+            {
+                let dist = match blast.iter().find(|(pt, _)| pt == pos) {
+                    Some((_, dist)) => *dist as i32,
+                    None => continue,
+                };
+                if victim_stats.health.hp <= 0 {
+                    continue;
+                }
+                let damage = roll * (radius + 1 - dist) / (radius + 1);
+                let seen = victim == *player || player_sees(*pos, &fov);
+                if seen {
+                    log.add(
+                        format!(
+                            "{} is caught in the blast for {} {} damage!",
+                            &name.name, damage, stats.damage_type
+                        ),
+                        white,
+                    );
+                }
+                SufferDamage::add_damage(
+                    &mut do_damage,
+                    victim,
+                    damage,
+                    stats.damage_type,
+                    thrower == *player,
+                    &format!("the blast of a {}", &name_wpn.name),
+                );
+                if ignited.contains(pos) {
+                    StatusEffects::add_status(
+                        &mut status_effects,
+                        victim,
+                        StatusType::Burning,
+                        BURNING_TURNS,
+                        thrower == *player,
+                    );
+                    if seen {
+                        log.add(format!("{} is {}!", &name.name, StatusType::Burning), white);
+                    }
+                }
+            }
+        }
+
+        // Blown doors change what everyone sees.
+        if doors_opened {
+            for fov in (&mut fov).join() {
+                fov.dirty = true;
+            }
+        }
+
+        throw_attack.clear();
+    }
+}
+
+/// Where a thrown missile aimed from `from` at `to` lands: it flies over everything but stops
+/// against opaque tiles (walls, closed doors, etc.) and at the weapon's range.
+pub fn landing_point(map: &Map, from: Point, to: Point, range: i32) -> Point {
+    let mut land = from;
+    for pt in line2d_bresenham(from, to).iter().filter(|pt| **pt != from) {
+        if !map.in_map_bounds(*pt)
+            || map.is_opaque(map.idx_pt(*pt))
+            || DistanceAlg::Pythagoras.distance2d(from, *pt) > range as f32
+        {
+            break;
+        }
+        land = *pt;
+    }
+
+    land
+}
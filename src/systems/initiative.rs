@@ -0,0 +1,92 @@
+use crate::components::{Energy, MeleeWeaponClass, MissileWeaponClass, Mob, MyTurn, Position};
+use crate::map_gen::{Map, TileType};
+use crate::state::RunState;
+use specs::prelude::*;
+
+/*
+ *
+ * initiative.rs
+ * -------------
+ * Decides who acts next. Every creature gains energy at its own speed as time passes, and acts
+ * once it has enough; each action spends as much energy as the time it takes, so fast creatures
+ * may act several times in a row, and slow actions give the others time to act.
+ *
+ */
+
+pub const ACTION_COST: i32 = 100; // Moving, waiting, unarmed attacks, etc.
+pub const RELOAD_COST: i32 = 150;
+const WADING_COST: i32 = 150; // Moving (or waiting) in shallow water.
+
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, RunState>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, Mob>,
+        ReadStorage<'a, Position>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, runstate, mut energies, mut my_turn, mobs, positions) = data;
+
+        if *runstate != RunState::MobTurn {
+            return;
+        }
+        my_turn.clear();
+
+        // Time passes until someone (on this level) can act. The player's turn comes once no mob
+        // is ready.
+        loop {
+            let ready = (&energies, &positions)
+                .join()
+                .any(|(energy, _)| energy.ready());
+            let still = (&energies, &positions)
+                .join()
+                .all(|(energy, _)| energy.speed <= 0);
+            if ready || still {
+                break;
+            }
+            for (energy, _) in (&mut energies, &positions).join() {
+                energy.energy += energy.speed;
+            }
+        }
+
+        for (ent, energy, _, _) in (&entities, &energies, &mobs, &positions).join() {
+            if energy.ready() {
+                my_turn
+                    .insert(ent, MyTurn {})
+                    .expect("Unable to insert turn");
+            }
+        }
+    }
+}
+
+/// Energy spent attacking with a melee weapon of the given class (None if unarmed).
+pub fn melee_cost(class: Option<MeleeWeaponClass>) -> i32 {
+    match class {
+        Some(MeleeWeaponClass::Dagger) => 80,
+        Some(MeleeWeaponClass::Axe) => 120,
+        Some(MeleeWeaponClass::Sword) | None => ACTION_COST,
+    }
+}
+
+/// Energy spent attacking with a missile weapon of the given class.
+pub fn missile_cost(class: MissileWeaponClass) -> i32 {
+    match class {
+        MissileWeaponClass::Rifle => 120,
+        MissileWeaponClass::Heavy => 150,
+        MissileWeaponClass::Pistol | MissileWeaponClass::Grenade => ACTION_COST,
+    }
+}
+
+/// Energy spent moving to (or staying at) `pos`.
+pub fn move_cost(map: &Map, pos: Position) -> i32 {
+    if map.tiles[map.idx(pos.x, pos.y)].ttype == TileType::ShallowWater {
+        WADING_COST
+    } else {
+        ACTION_COST
+    }
+}
@@ -1,5 +1,8 @@
+use super::status::roll_on_hit;
+use crate::common::is_seen_by;
 use crate::components::{
-    ActiveWeapon, BaseStats, Equipment, MeleeAttack, MeleeWeapon, Name, SufferDamage,
+    ActiveWeapon, Attributes, BaseStats, Equipment, Fov, MeleeAttack, MeleeWeapon, Name, Position,
+    Skills, StatusEffects, SufferDamage, Survival,
 };
 use crate::log::Log;
 use crate::utils::colors::*;
@@ -14,6 +17,10 @@ use specs::prelude::*;
  *
  */
 
+const BASE_HIT_CHANCE: i32 = 85; // %, between equally agile fighters.
+const AGILITY_BONUS: i32 = 5; // For each point of difference between the fighters' agility.
+const SKILL_BONUS: i32 = 3; // For each level of the attacker's skill with the weapon.
+
 pub struct MeleeSystem {}
 
 impl<'a> System<'a> for MeleeSystem {
@@ -29,6 +36,11 @@ impl<'a> System<'a> for MeleeSystem {
         WriteExpect<'a, Log>,
         WriteExpect<'a, RandomNumberGenerator>,
         ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Fov>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, Skills>,
+        ReadStorage<'a, Survival>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -44,6 +56,11 @@ impl<'a> System<'a> for MeleeSystem {
             mut log,
             mut rng,
             names,
+            positions,
+            fov,
+            mut status_effects,
+            mut skills,
+            survival,
         ) = data;
         let white = color("BrightWhite", 1.0);
 
@@ -53,7 +70,13 @@ impl<'a> System<'a> for MeleeSystem {
             let attacker_hp = attacker_stats.health.hp;
             let victim_stats = base_stats.get(melee.target).unwrap();
             let victim_hp = victim_stats.health.hp;
+            // Fights between mobs are only logged when the player sees them.
+            let seen = entity == *player
+                || melee.target == *player
+                || is_seen_by(entity, *player, &positions, &fov)
+                || is_seen_by(melee.target, *player, &positions, &fov);
             let victim_name = names.get(melee.target).unwrap();
+            let penalty = survival.get(entity).map_or(0, |s| s.hit_penalty());
 
             let mut has_weapon_equipped = false;
 
@@ -64,30 +87,89 @@ impl<'a> System<'a> for MeleeSystem {
                     if equip.user == entity {
                         has_weapon_equipped = true;
                         let wpn_stats = &melee_wpn.stats;
-                        let total_intended_damage = rng
-                            .roll_dice(wpn_stats.dice_n, wpn_stats.dice_faces)
-                            + wpn_stats.dice_bonus;
+                        let class = melee_wpn.class;
+                        let skill = skills.get(entity).map_or(0, |s| s.melee_level(class));
+                        if let Some(level) =
+                            skills.get_mut(entity).and_then(|s| s.train_melee(class))
+                        {
+                            if entity == *player {
+                                log.add(
+                                    format!(
+                                        "Your {} skill improves to {}.",
+                                        class.to_string().to_lowercase(),
+                                        level
+                                    ),
+                                    color("BrightYellow", 1.0),
+                                );
+                            }
+                        }
+                        if rng.range(0, 100)
+                            >= hit_chance(attacker_stats, victim_stats, skill, penalty)
+                        {
+                            if seen {
+                                log.add(
+                                    format!(
+                                        "{} swings {} at {}, but misses.",
+                                        &name.name, &name_wpn.name, &victim_name.name
+                                    ),
+                                    white,
+                                );
+                            }
+                            break;
+                        }
+                        let total_intended_damage =
+                            wpn_stats.damage.roll(&mut rng) + damage_bonus(attacker_stats, skill);
                         let damage = i32::max(0, total_intended_damage - victim_stats.defense);
-                        log.add(
-                            format!(
-                                "{} hits {} with {} for {} hp!",
-                                &name.name, &victim_name.name, &name_wpn.name, damage
-                            ),
-                            white,
-                        );
+                        if seen {
+                            log.add(
+                                format!(
+                                    "{} hits {} with {} for {} {} damage!",
+                                    &name.name,
+                                    &victim_name.name,
+                                    &name_wpn.name,
+                                    damage,
+                                    wpn_stats.damage_type
+                                ),
+                                white,
+                            );
+                        }
                         SufferDamage::add_damage(
                             &mut do_damage,
                             melee.target,
                             damage,
+                            wpn_stats.damage_type,
                             entity == *player,
+                            &format!("{}'s {}", &name.name, &name_wpn.name),
                         );
+                        if damage > 0 {
+                            if let Some(status) = roll_on_hit(
+                                wpn_stats,
+                                melee.target,
+                                entity == *player,
+                                &mut status_effects,
+                                &mut rng,
+                            ) {
+                                if seen {
+                                    log.add(format!("{} is {}!", &victim_name.name, status), white);
+                                }
+                            }
+                        }
                         break;
                     }
                 }
                 if !has_weapon_equipped {
                     let attack = &attacker_stats.attack;
+                    if rng.range(0, 100) >= hit_chance(attacker_stats, victim_stats, 0, penalty) {
+                        if seen {
+                            log.add(
+                                format!("{} misses {}.", &name.name, &victim_name.name),
+                                white,
+                            );
+                        }
+                        continue;
+                    }
                     let total_intended_damage =
-                        rng.roll_dice(attack.dice_n, attack.dice_faces) + attack.dice_bonus;
+                        attack.damage.roll(&mut rng) + damage_bonus(attacker_stats, 0);
                     let damage = i32::max(0, total_intended_damage - victim_stats.defense);
                     let physical_attack_names = vec![
                         "hits",
@@ -106,25 +188,54 @@ impl<'a> System<'a> for MeleeSystem {
                         "slaps",
                         "jumps on",
                     ];
-                    log.add(
-                        format!(
-                            "{} {} {} for {} hp!",
-                            &name.name,
-                            rng.random_slice_entry(&physical_attack_names).unwrap(),
-                            &victim_name.name,
-                            damage
-                        ),
-                        white,
-                    );
+                    let verb = rng.random_slice_entry(&physical_attack_names).unwrap();
+                    if seen {
+                        log.add(
+                            format!(
+                                "{} {} {} for {} {} damage!",
+                                &name.name, verb, &victim_name.name, damage, attack.damage_type
+                            ),
+                            white,
+                        );
+                    }
                     SufferDamage::add_damage(
                         &mut do_damage,
                         melee.target,
                         damage,
+                        attack.damage_type,
                         entity == *player,
+                        &name.name,
                     );
+                    if damage > 0 {
+                        if let Some(status) = roll_on_hit(
+                            attack,
+                            melee.target,
+                            entity == *player,
+                            &mut status_effects,
+                            &mut rng,
+                        ) {
+                            if seen {
+                                log.add(format!("{} is {}!", &victim_name.name, status), white);
+                            }
+                        }
+                    }
                 }
             }
         }
         melee_attack.clear();
     }
 }
+
+/// Chance (%) of a melee attack hitting, which depends on how agile both fighters are, on the
+/// attacker's `skill` with the weapon and on any `penalty` it suffers (hunger, thirst, fatigue).
+pub fn hit_chance(attacker: &BaseStats, defender: &BaseStats, skill: i32, penalty: i32) -> i32 {
+    let agility_diff = attacker.attributes.agility - defender.attributes.agility;
+    let chance = BASE_HIT_CHANCE + AGILITY_BONUS * agility_diff + SKILL_BONUS * skill - penalty;
+
+    chance.clamp(5, 95)
+}
+
+/// Extra damage of a melee hit, from the attacker's strength and `skill` with the weapon.
+fn damage_bonus(attacker: &BaseStats, skill: i32) -> i32 {
+    attacker.attack_bonus + Attributes::bonus(attacker.attributes.strength) + skill / 2
+}
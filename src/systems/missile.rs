@@ -1,9 +1,15 @@
+use super::status::roll_on_hit;
+use crate::common::is_seen_by;
 use crate::components::{
-    ActiveWeapon, BaseStats, Equipment, MissileAttack, MissileWeapon, Name, SufferDamage,
+    ActiveWeapon, Attributes, BaseStats, Equipment, Fov, MissileAttack, MissileWeapon, Name,
+    Position, Projectile, Skills, StatusEffects, SufferDamage, Survival,
 };
 use crate::log::Log;
+use crate::map_gen::Map;
 use crate::utils::colors::*;
-use bracket_lib::prelude::RandomNumberGenerator;
+use bracket_lib::prelude::{
+    line2d_bresenham, to_cp437, BaseMap, DistanceAlg, Point, RandomNumberGenerator,
+};
 use specs::prelude::*;
 
 /*
@@ -14,6 +20,83 @@ use specs::prelude::*;
  *
  */
 
+const BASE_HIT_CHANCE: i32 = 90; // %, point blank.
+const MAX_RANGE_PENALTY: i32 = 30; // At the weapon's range.
+const OUT_OF_RANGE_PENALTY: i32 = 15; // For each tile beyond the weapon's range.
+const COVER_PENALTY: i32 = 20; // For each blocker in the way.
+const DEFENSE_PENALTY: i32 = 3; // For each point of the defender's defense.
+const WOUNDED_PENALTY: i32 = 10; // If the attacker is below half its max hp.
+const PERCEPTION_BONUS: i32 = 5; // For each point of the attacker's perception bonus.
+const SKILL_BONUS: i32 = 3; // For each level of the attacker's skill with the weapon.
+const STRAY_HIT_CHANCE: i32 = 50; // %, of the usual chance, for whoever is in the way.
+
+/// Chance (%) of hitting `to` from `from` with a missile weapon of the given range. It drops with
+/// distance, and faster past the weapon's range, for each blocker in the line of fire, for the
+/// defender's defense and when the attacker is badly hurt; it rises with the attacker's
+/// perception and `skill` with the weapon, and drops by any `penalty` the attacker suffers
+/// (hunger, thirst, fatigue). Targets behind opaque tiles can't be hit at all.
+#[allow(clippy::too_many_arguments)]
+pub fn hit_chance(
+    map: &Map,
+    from: Point,
+    to: Point,
+    range: i32,
+    attacker: &BaseStats,
+    defender: &BaseStats,
+    skill: i32,
+    penalty: i32,
+) -> i32 {
+    let cover = match map.cover_between(from, to) {
+        Some(cover) => cover,
+        None => return 0,
+    };
+    let range = range.max(1) as f32;
+    let dist = DistanceAlg::Pythagoras.distance2d(from, to);
+
+    let mut chance = BASE_HIT_CHANCE - (MAX_RANGE_PENALTY as f32 * dist.min(range) / range) as i32;
+    if dist > range {
+        chance -= OUT_OF_RANGE_PENALTY * (dist - range).ceil() as i32;
+    }
+    chance -= COVER_PENALTY * cover;
+    chance -= DEFENSE_PENALTY * defender.defense;
+    if attacker.health.hp * 2 < attacker.health.max_hp {
+        chance -= WOUNDED_PENALTY;
+    }
+    chance += PERCEPTION_BONUS * Attributes::bonus(attacker.attributes.perception);
+    chance += SKILL_BONUS * skill;
+    chance -= penalty;
+
+    chance.clamp(5, 95)
+}
+
+/// Tiles a projectile shot from `from` at `to` flies over, in order. It keeps going past the
+/// target, up to the weapon's range, and stops at the first opaque tile (included) or the map's
+/// edge.
+pub fn projectile_path(map: &Map, from: Point, to: Point, range: i32) -> Vec<Point> {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let steps = i32::max(dx.abs(), dy.abs());
+    if steps == 0 {
+        return Vec::new();
+    }
+    let max_dist = f32::max(range as f32, DistanceAlg::Pythagoras.distance2d(from, to));
+    // Stretch the line so it reaches as far as the projectile can fly.
+    let k = (max_dist / steps as f32).ceil() as i32 + 1;
+    let far = Point::new(from.x + dx * k, from.y + dy * k);
+
+    let mut path = Vec::new();
+    for pt in line2d_bresenham(from, far).into_iter().skip(1) {
+        if !map.in_map_bounds(pt) || DistanceAlg::Pythagoras.distance2d(from, pt) > max_dist {
+            break;
+        }
+        path.push(pt);
+        if map.is_opaque(map.idx_pt(pt)) {
+            break;
+        }
+    }
+
+    path
+}
+
 pub struct MissileSystem {}
 
 impl<'a> System<'a> for MissileSystem {
@@ -29,6 +112,13 @@ impl<'a> System<'a> for MissileSystem {
         WriteExpect<'a, Log>,
         WriteExpect<'a, RandomNumberGenerator>,
         ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Fov>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Skills>,
+        ReadStorage<'a, Survival>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -44,6 +134,13 @@ impl<'a> System<'a> for MissileSystem {
             mut log,
             mut rng,
             names,
+            positions,
+            fov,
+            map,
+            mut status_effects,
+            mut projectiles,
+            mut skills,
+            survival,
         ) = data;
         let white = color("BrightWhite", 1.0);
 
@@ -53,37 +150,166 @@ impl<'a> System<'a> for MissileSystem {
             let attacker_hp = attacker_stats.health.hp;
             let victim_stats = base_stats.get(missile.target).unwrap();
             let victim_hp = victim_stats.health.hp;
+            // Fights between mobs are only logged when the player sees them.
+            let seen = entity == *player
+                || missile.target == *player
+                || is_seen_by(entity, *player, &positions, &fov)
+                || is_seen_by(missile.target, *player, &positions, &fov);
 
             if attacker_hp > 0 && victim_hp > 0 {
                 for (_active_wpn, missile_wpn, equip, name_wpn) in
                     (&active_wpn, &mut missile_wpns, &equipment, &names).join()
                 {
                     if equip.user == entity && missile_wpn.ammo.ammo > 0 {
-                        let wpn_stats = &missile_wpn.stats;
-                        let total_intended_damage = rng
-                            .roll_dice(wpn_stats.dice_n, wpn_stats.dice_faces)
-                            + wpn_stats.dice_bonus;
-                        let damage = i32::max(0, total_intended_damage - victim_stats.defense);
                         missile_wpn.ammo.ammo -= 1;
+                        let wpn_stats = &missile_wpn.stats;
+                        let class = missile_wpn.class;
+                        let skill = skills.get(entity).map_or(0, |s| s.missile_level(class));
+                        let penalty = survival.get(entity).map_or(0, |s| s.hit_penalty());
+                        if let Some(level) =
+                            skills.get_mut(entity).and_then(|s| s.train_missile(class))
+                        {
+                            if entity == *player {
+                                log.add(
+                                    format!(
+                                        "Your {} skill improves to {}.",
+                                        class.to_string().to_lowercase(),
+                                        level
+                                    ),
+                                    color("BrightYellow", 1.0),
+                                );
+                            }
+                        }
                         let victim_name = names.get(missile.target).unwrap();
-                        log.add(
-                            format!(
-                                "{} shoots {} with a {} for {} hp!",
-                                &name.name, &victim_name.name, &name_wpn.name, damage
-                            ),
-                            white,
-                        );
+                        let (from, to) =
+                            match (positions.get(entity), positions.get(missile.target)) {
+                                (Some(from), Some(to)) => (*from, *to),
+                                _ => break,
+                            };
+
+                        // The projectile flies along the line of fire until it hits someone, or
+                        // something that blocks it. Whoever is in the way may be hit instead.
+                        let mut hit: Option<Entity> = None;
+                        let mut end = from;
+                        for pt in projectile_path(&map, from, to, wpn_stats.range) {
+                            end = pt;
+                            let idx = map.idx_pt(pt);
+                            let creature = (&entities, &positions, &base_stats).join().find(
+                                |(ent, pos, stats)| {
+                                    *ent != entity && **pos == pt && stats.health.hp > 0
+                                },
+                            );
+                            if let Some((creature, _, creature_stats)) = creature {
+                                let mut chance = hit_chance(
+                                    &map,
+                                    from,
+                                    pt,
+                                    wpn_stats.range,
+                                    attacker_stats,
+                                    creature_stats,
+                                    skill,
+                                    penalty,
+                                );
+                                if creature != missile.target {
+                                    chance = chance * STRAY_HIT_CHANCE / 100;
+                                }
+                                if rng.range(0, 100) < chance {
+                                    hit = Some(creature);
+                                    break;
+                                }
+                            } else if map.tiles[idx].block || map.is_opaque(idx) {
+                                break;
+                            }
+                        }
+                        entities
+                            .build_entity()
+                            .with(
+                                Projectile {
+                                    path: line2d_bresenham(from, end),
+                                    glyph: to_cp437('∙'),
+                                    fg: color("BrightYellow", 1.0),
+                                    elapsed: 0.0,
+                                },
+                                &mut projectiles,
+                            )
+                            .build();
+
+                        let victim = match hit {
+                            Some(victim) => victim,
+                            None => {
+                                if seen {
+                                    log.add(
+                                        format!(
+                                            "{} shoots at {} with a {}, but misses.",
+                                            &name.name, &victim_name.name, &name_wpn.name
+                                        ),
+                                        white,
+                                    );
+                                }
+                                break;
+                            }
+                        };
+                        let hit_name = names.get(victim).unwrap();
+                        let hit_stats = base_stats.get(victim).unwrap();
+
+                        let total_intended_damage = wpn_stats.damage.roll(&mut rng)
+                            + attacker_stats.attack_bonus
+                            + skill / 2;
+                        let damage = i32::max(0, total_intended_damage - hit_stats.defense);
+                        if victim == missile.target {
+                            if seen {
+                                log.add(
+                                    format!(
+                                        "{} shoots {} with a {} for {} {} damage!",
+                                        &name.name,
+                                        &victim_name.name,
+                                        &name_wpn.name,
+                                        damage,
+                                        wpn_stats.damage_type
+                                    ),
+                                    white,
+                                );
+                            }
+                        } else if seen
+                            || victim == *player
+                            || is_seen_by(victim, *player, &positions, &fov)
+                        {
+                            log.add(
+                                format!(
+                                    "{} shoots at {}, but hits {} for {} {} damage!",
+                                    &name.name,
+                                    &victim_name.name,
+                                    &hit_name.name,
+                                    damage,
+                                    wpn_stats.damage_type
+                                ),
+                                white,
+                            );
+                        }
                         SufferDamage::add_damage(
                             &mut do_damage,
-                            missile.target,
+                            victim,
                             damage,
+                            wpn_stats.damage_type,
                             entity == *player,
+                            &format!("{}'s {}", &name.name, &name_wpn.name),
                         );
-                        break;
-                    } else {
-                        if entity == *player {
-                            log.add(format!("No ammo for {}.", &name_wpn.name), white);
+                        if damage > 0 {
+                            if let Some(status) = roll_on_hit(
+                                wpn_stats,
+                                victim,
+                                entity == *player,
+                                &mut status_effects,
+                                &mut rng,
+                            ) {
+                                if seen {
+                                    log.add(format!("{} is {}!", &hit_name.name, status), white);
+                                }
+                            }
                         }
+                        break;
+                    } else if equip.user == entity && entity == *player {
+                        log.add(format!("No ammo for {}.", &name_wpn.name), white);
                     }
                 }
             }
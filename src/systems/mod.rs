@@ -2,10 +2,14 @@ pub mod ai;
 pub mod consumable;
 pub mod damage;
 pub mod equipment;
+pub mod explosion;
 pub mod fov;
+pub mod initiative;
 pub mod item_collect;
 pub mod item_drop;
 pub mod mapping;
 pub mod melee;
 pub mod missile;
+pub mod status;
+pub mod survival;
 pub mod weapon_reload;
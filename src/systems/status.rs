@@ -0,0 +1,130 @@
+use crate::common::is_seen_by;
+use crate::components::{
+    Attack, Fov, MyTurn, Name, Position, StatusEffects, StatusType, SufferDamage,
+};
+use crate::log::Log;
+use crate::state::RunState;
+use crate::utils::colors::*;
+use bracket_lib::prelude::RandomNumberGenerator;
+use specs::prelude::*;
+
+/*
+ *
+ * status.rs
+ * ---------
+ * Ticks the timed status effects (bleeding, poison, etc.) once per turn of whoever suffers them,
+ * dealing their damage and removing the ones that wore off.
+ *
+ */
+
+pub struct StatusSystem {}
+
+impl<'a> System<'a> for StatusSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Fov>,
+        ReadStorage<'a, MyTurn>,
+        WriteExpect<'a, Log>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            runstate,
+            mut status_effects,
+            mut do_damage,
+            names,
+            positions,
+            fov,
+            my_turn,
+            mut log,
+        ) = data;
+        let white = color("BrightWhite", 1.0);
+
+        // The player's effects tick on its turn, the mobs' on theirs (when they have the energy to
+        // act).
+        let players_turn = match *runstate {
+            RunState::PlayerTurn => true,
+            RunState::MobTurn => false,
+            _ => return,
+        };
+
+        let mut worn_off: Vec<Entity> = Vec::new();
+        for (ent, statuses, name) in (&entities, &mut status_effects, &names).join() {
+            if (ent == *player) != players_turn || (!players_turn && !my_turn.contains(ent)) {
+                continue;
+            }
+            let seen = ent == *player || is_seen_by(ent, *player, &positions, &fov);
+
+            for effect in statuses.effects.iter_mut() {
+                if let Some((amount, dtype)) = effect.status.damage() {
+                    let source = match effect.status {
+                        StatusType::Bleeding => "blood loss",
+                        StatusType::Poisoned => "poison",
+                        _ => "burns",
+                    };
+                    SufferDamage::add_damage(
+                        &mut do_damage,
+                        ent,
+                        amount,
+                        dtype,
+                        effect.from_player,
+                        source,
+                    );
+                    if seen {
+                        log.add(
+                            format!("{} suffers {} {} damage.", &name.name, amount, dtype),
+                            white,
+                        );
+                    }
+                }
+                effect.turns -= 1;
+                if effect.turns <= 0 && seen {
+                    log.add(
+                        format!("{} is no longer {}.", &name.name, effect.status),
+                        white,
+                    );
+                }
+            }
+
+            statuses.effects.retain(|e| e.turns > 0);
+            if statuses.effects.is_empty() {
+                worn_off.push(ent);
+            }
+        }
+
+        for ent in worn_off {
+            status_effects.remove(ent);
+        }
+    }
+}
+
+/// Rolls the attack's on-hit status against the victim. Returns it if it was inflicted.
+pub fn roll_on_hit(
+    attack: &Attack,
+    victim: Entity,
+    from_player: bool,
+    status_effects: &mut WriteStorage<StatusEffects>,
+    rng: &mut RandomNumberGenerator,
+) -> Option<StatusType> {
+    let on_hit = attack.on_hit.as_ref()?;
+    if rng.range(0, 100) >= on_hit.chance {
+        return None;
+    }
+    StatusEffects::add_status(
+        status_effects,
+        victim,
+        on_hit.status,
+        on_hit.turns,
+        from_player,
+    );
+
+    Some(on_hit.status)
+}
@@ -0,0 +1,107 @@
+use crate::components::{
+    DamageType, Energy, RunStats, SufferDamage, Survival, SURVIVAL_LOW, SURVIVAL_MAX, TURN_ENERGY,
+};
+use crate::log::Log;
+use crate::state::RunState;
+use crate::utils::colors::*;
+use specs::prelude::*;
+
+/*
+ *
+ * survival.rs
+ * -----------
+ * Drains the player's hunger, thirst and fatigue meters every turn (fatigue recovers while
+ * resting instead), and makes the player pay for letting them run out.
+ *
+ */
+
+const HUNGER_DRAIN: i32 = 1;
+const THIRST_DRAIN: i32 = 2;
+const FATIGUE_DRAIN: i32 = 1;
+const REST_RECOVERY: i32 = 10; // Fatigue recovered for each turn of rest.
+const DEPRIVATION_INTERVAL: i32 = 10; // Turns between each point of damage from starving or thirst.
+const EXHAUSTED_SPEED: i32 = 75; // With no fatigue left.
+
+pub struct SurvivalSystem {}
+
+impl<'a> System<'a> for SurvivalSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, RunState>,
+        WriteStorage<'a, Survival>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, RunStats>,
+        WriteExpect<'a, Log>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, runstate, mut survival, mut energies, mut do_damage, run_stats, mut log) =
+            data;
+
+        // Only the player has survival meters, drained on its turn.
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        for (ent, meters) in (&entities, &mut survival).join() {
+            let before = meters.clone();
+            meters.hunger = i32::max(0, meters.hunger - HUNGER_DRAIN);
+            meters.thirst = i32::max(0, meters.thirst - THIRST_DRAIN);
+            if meters.resting {
+                meters.fatigue = i32::min(SURVIVAL_MAX, meters.fatigue + REST_RECOVERY);
+                if meters.fatigue == SURVIVAL_MAX {
+                    meters.resting = false;
+                    log.add("You feel rested.", color("BrightWhite", 1.0));
+                }
+            } else {
+                meters.fatigue = i32::max(0, meters.fatigue - FATIGUE_DRAIN);
+            }
+
+            let warnings = [
+                (before.hunger, meters.hunger, "getting hungry", "starving"),
+                (before.thirst, meters.thirst, "getting thirsty", "parched"),
+                (before.fatigue, meters.fatigue, "getting tired", "exhausted"),
+            ];
+            for &(before, after, low, empty) in warnings.iter() {
+                if before > 0 && after == 0 {
+                    log.add(format!("You are {}!", empty), color("BrightRed", 1.0));
+                } else if before >= SURVIVAL_LOW && after < SURVIVAL_LOW {
+                    log.add(format!("You are {}.", low), color("BrightYellow", 1.0));
+                }
+            }
+
+            let turns = run_stats.get(ent).map_or(0, |stats| stats.turns);
+            if turns % DEPRIVATION_INTERVAL == 0 {
+                if meters.hunger == 0 {
+                    SufferDamage::add_damage(
+                        &mut do_damage,
+                        ent,
+                        1,
+                        DamageType::Hunger,
+                        false,
+                        "starvation",
+                    );
+                }
+                if meters.thirst == 0 {
+                    SufferDamage::add_damage(
+                        &mut do_damage,
+                        ent,
+                        1,
+                        DamageType::Thirst,
+                        false,
+                        "thirst",
+                    );
+                }
+            }
+
+            if let Some(energy) = energies.get_mut(ent) {
+                energy.speed = if meters.fatigue == 0 {
+                    EXHAUSTED_SPEED
+                } else {
+                    TURN_ENERGY
+                };
+            }
+        }
+    }
+}
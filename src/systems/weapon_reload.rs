@@ -1,13 +1,18 @@
-use crate::components::{AmmoType, Ammunition, Inventory, MissileWeapon, Name, TryReload};
+use crate::common::is_seen_by;
+use crate::components::{
+    Ammunition, Attributes, BaseStats, Fov, Inventory, MissileWeapon, Name, Position, Skills,
+    TryReload,
+};
 use crate::log::Log;
 use crate::utils::colors::*;
 use specs::prelude::*;
 
 /*
  *
- * missile.rs
- * ----------
- * Resposible for managing every missile (ranged) attack performed.
+ * weapon_reload.rs
+ * ----------------
+ * Reloads missile weapons with the ammunition in their user's inventory. Agile and skilled users
+ * load more rounds at once.
  *
  */
 
@@ -23,6 +28,10 @@ impl<'a> System<'a> for WeaponReloadSystem {
         ReadExpect<'a, Entity>,
         WriteExpect<'a, Log>,
         ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Fov>,
+        ReadStorage<'a, BaseStats>,
+        ReadStorage<'a, Skills>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -35,38 +44,59 @@ impl<'a> System<'a> for WeaponReloadSystem {
             player,
             mut log,
             names,
+            positions,
+            fov,
+            base_stats,
+            skills,
         ) = data;
 
         for (ent, reload) in (&entities, &try_reload).join() {
             if let Some(w) = missile_weapon.get_mut(reload.weapon) {
-                let ammo_type = &w.ammo.ammo_type;
-                for (e, _inv) in (&entities, &inventory).join() {
+                let skill = skills.get(ent).map_or(0, |s| s.missile_level(w.class));
+                let agility = base_stats
+                    .get(ent)
+                    .map_or(0, |s| Attributes::bonus(s.attributes.agility));
+                let mut rounds = reload_rounds(agility, skill);
+                let mut loaded = 0;
+                for (e, inv) in (&entities, &inventory).join() {
+                    if inv.owner != ent || rounds == 0 || w.ammo.ammo >= w.ammo.max_ammo {
+                        continue;
+                    }
                     if let Some(amm) = ammo.get_mut(e) {
-                        if amm.ammo_type == *ammo_type && amm.ammo > 0 {
-                            match ammo_type {
-                                AmmoType::_32 => {
-                                    amm.ammo -= 1;
-                                    if amm.ammo == 0 {
-                                        entities.delete(e).ok();
-                                    }
-                                    w.ammo.ammo += 1;
-                                    if ent == *player {
-                                        log.add(
-                                            format!(
-                                                "You reload the {}.",
-                                                names.get(reload.weapon).unwrap().name
-                                            ),
-                                            color("BrightWhite", 1.0),
-                                        );
-                                    }
-                                }
-                                _ => {}
+                        if amm.ammo_type == w.ammo.ammo_type && amm.ammo > 0 {
+                            let n = rounds.min(amm.ammo).min(w.ammo.max_ammo - w.ammo.ammo);
+                            amm.ammo -= n;
+                            if amm.ammo == 0 {
+                                entities.delete(e).ok();
                             }
+                            w.ammo.ammo += n;
+                            rounds -= n;
+                            loaded += n;
                         }
                     }
                 }
+
+                if loaded > 0 {
+                    let wpn_name = &names.get(reload.weapon).unwrap().name;
+                    if ent == *player {
+                        log.add(
+                            format!("You reload the {} ({}).", wpn_name, loaded),
+                            color("BrightWhite", 1.0),
+                        );
+                    } else if is_seen_by(ent, *player, &positions, &fov) {
+                        log.add(
+                            format!("{} reloads the {}.", names.get(ent).unwrap().name, wpn_name),
+                            color("BrightWhite", 1.0),
+                        );
+                    }
+                }
             }
         }
         try_reload.clear();
     }
 }
+
+/// Rounds loaded in a single reload, given the user's agility bonus and skill with the weapon.
+fn reload_rounds(agility: i32, skill: i32) -> i32 {
+    1 + agility.max(0) / 2 + skill / 3
+}
@@ -0,0 +1,271 @@
+use super::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::raws::RAWS;
+use crate::state::RunSeed;
+use crate::utils::colors::*;
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+/*
+ *
+ * character.rs
+ * ------------
+ * Character creation, after "New Journey": the player names the character, picks one of the
+ * backgrounds in the raws and may set the seed of the run.
+ *
+ */
+
+const MAX_NAME_LEN: usize = 20;
+const MAX_SEED_LEN: usize = 16; // Hex digits in a u64.
+
+/// The character being created, until the journey begins.
+#[derive(Clone)]
+pub struct CharacterDraft {
+    pub name: String,
+    pub background: usize, // Index in the raws' backgrounds.
+    pub seed: String,      // Hexadecimal, as passed to `--seed`. Blank keeps the rolled one.
+    pub editing_seed: bool,
+}
+
+impl Default for CharacterDraft {
+    fn default() -> Self {
+        Self {
+            name: "Severian".to_string(),
+            background: 0,
+            seed: String::new(),
+            editing_seed: false,
+        }
+    }
+}
+
+impl CharacterDraft {
+    /// The seed typed in, if any.
+    pub fn seed(&self) -> Option<u64> {
+        u64::from_str_radix(&self.seed, 16).ok()
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CharacterResult {
+    Editing,
+    Cancel,
+    Done,
+}
+
+pub fn show_character_creation(
+    ecs: &World,
+    term: &mut BTerm,
+    draw_batch: &mut DrawBatch,
+) -> CharacterResult {
+    let mut draft = ecs.fetch_mut::<CharacterDraft>();
+    let raws = RAWS.lock().unwrap();
+    let backgrounds = &raws.raws.backgrounds;
+
+    let bg = color("Background", 1.0);
+    let white = ColorPair::new(color("White", 1.0), bg);
+    let bright = ColorPair::new(color("BrightWhite", 1.0), bg);
+    let gray = ColorPair::new(color("BrightBlack", 1.0), bg);
+    let cyan = ColorPair::new(color("Cyan", 0.8), bg);
+
+    draw_batch.draw_hollow_box(
+        Rect::with_size(0, 0, WINDOW_WIDTH - 1, WINDOW_HEIGHT - 1),
+        gray,
+    );
+    draw_batch.print_color_centered(
+        8,
+        "WHO ARE YOU, WANDERER?",
+        ColorPair::new(color("Green", 1.0), bg),
+    );
+
+    let cursor = |editing: bool| if editing { "_" } else { "" };
+    draw_batch.print_color(Point::new(10, 14), "Name:", gray);
+    draw_batch.print_color(
+        Point::new(16, 14),
+        format!("{}{}", draft.name, cursor(!draft.editing_seed)),
+        bright,
+    );
+    draw_batch.print_color(Point::new(10, 15), "Seed:", gray);
+    if draft.seed.is_empty() && !draft.editing_seed {
+        // The seed already rolled for the run.
+        let rolled = format!("{:x} (random)", ecs.fetch::<RunSeed>().0);
+        draw_batch.print_color(Point::new(16, 15), rolled, gray);
+    } else {
+        draw_batch.print_color(
+            Point::new(16, 15),
+            format!("{}{}", draft.seed, cursor(draft.editing_seed)),
+            bright,
+        );
+    }
+
+    draw_batch.print_color(Point::new(10, 18), "Background:", gray);
+    for (i, background) in backgrounds.iter().enumerate() {
+        let fg = if i == draft.background { cyan } else { white };
+        draw_batch.print_color(Point::new(12, 20 + i as i32), &background.name, fg);
+    }
+
+    if let Some(background) = backgrounds.get(draft.background) {
+        let x = 36;
+        let mut y = 18;
+        for line in background.descr.lines() {
+            draw_batch.print_color(Point::new(x, y), line, white);
+            y += 1;
+        }
+        let stats = &background.stats;
+        let attributes = &stats.attributes;
+        y += 1;
+        draw_batch.print_color(
+            Point::new(x, y),
+            format!("HP {}  DEF {}", stats.max_hp, stats.defense),
+            bright,
+        );
+        draw_batch.print_color(
+            Point::new(x, y + 1),
+            format!(
+                "STR {}  AGI {}  PER {}",
+                attributes.strength, attributes.agility, attributes.perception
+            ),
+            bright,
+        );
+        y += 3;
+        draw_batch.print_color(Point::new(x, y), "Equipment:", gray);
+        for item in background.equips.iter() {
+            y += 1;
+            draw_batch.print_color(Point::new(x + 2, y), item, white);
+        }
+        y += 2;
+        draw_batch.print_color(Point::new(x, y), "Inventory:", gray);
+        for item in background.inventory.iter() {
+            y += 1;
+            draw_batch.print_color(Point::new(x + 2, y), item, white);
+        }
+    }
+
+    draw_batch.print_color_centered(
+        WINDOW_HEIGHT - 7,
+        "Type a name and choose a background with the arrow keys.",
+        gray,
+    );
+    draw_batch.print_color_centered(
+        WINDOW_HEIGHT - 6,
+        "Tab to type a seed (hexadecimal), or leave it blank.",
+        gray,
+    );
+    draw_batch.print_color_centered(
+        WINDOW_HEIGHT - 5,
+        "Enter to begin the journey, Escape to go back.",
+        gray,
+    );
+
+    match term.key {
+        None => CharacterResult::Editing,
+        Some(key) => match key {
+            VirtualKeyCode::Escape => CharacterResult::Cancel,
+            VirtualKeyCode::Return => {
+                if draft.name.trim().is_empty() || backgrounds.is_empty() {
+                    CharacterResult::Editing
+                } else {
+                    draft.name = draft.name.trim().to_string();
+                    CharacterResult::Done
+                }
+            }
+            VirtualKeyCode::Up => {
+                let n = backgrounds.len().max(1);
+                draft.background = (draft.background + n - 1) % n;
+                CharacterResult::Editing
+            }
+            VirtualKeyCode::Down => {
+                draft.background = (draft.background + 1) % backgrounds.len().max(1);
+                CharacterResult::Editing
+            }
+            VirtualKeyCode::Tab => {
+                draft.editing_seed = !draft.editing_seed;
+                CharacterResult::Editing
+            }
+            VirtualKeyCode::Back => {
+                if draft.editing_seed {
+                    draft.seed.pop();
+                } else {
+                    draft.name.pop();
+                }
+                CharacterResult::Editing
+            }
+            _ if draft.editing_seed => {
+                if let Some(c) = key_to_hex_digit(key) {
+                    if draft.seed.len() < MAX_SEED_LEN {
+                        draft.seed.push(c);
+                    }
+                }
+                CharacterResult::Editing
+            }
+            _ => {
+                if let Some(c) = key_to_char(key, term.shift) {
+                    if draft.name.chars().count() < MAX_NAME_LEN {
+                        draft.name.push(c);
+                    }
+                }
+                CharacterResult::Editing
+            }
+        },
+    }
+}
+
+/// The hexadecimal digit typed with `key`, for seeds.
+fn key_to_hex_digit(key: VirtualKeyCode) -> Option<char> {
+    let c = match key {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => '0',
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => '1',
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => '2',
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => '3',
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => '4',
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => '5',
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => '6',
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => '7',
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => '8',
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => '9',
+        VirtualKeyCode::A => 'a',
+        VirtualKeyCode::B => 'b',
+        VirtualKeyCode::C => 'c',
+        VirtualKeyCode::D => 'd',
+        VirtualKeyCode::E => 'e',
+        VirtualKeyCode::F => 'f',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+/// The character typed with `key`, for the keys allowed in names.
+fn key_to_char(key: VirtualKeyCode, shift: bool) -> Option<char> {
+    let c = match key {
+        VirtualKeyCode::Space => return Some(' '),
+        VirtualKeyCode::Minus => return Some('-'),
+        VirtualKeyCode::A => 'a',
+        VirtualKeyCode::B => 'b',
+        VirtualKeyCode::C => 'c',
+        VirtualKeyCode::D => 'd',
+        VirtualKeyCode::E => 'e',
+        VirtualKeyCode::F => 'f',
+        VirtualKeyCode::G => 'g',
+        VirtualKeyCode::H => 'h',
+        VirtualKeyCode::I => 'i',
+        VirtualKeyCode::J => 'j',
+        VirtualKeyCode::K => 'k',
+        VirtualKeyCode::L => 'l',
+        VirtualKeyCode::M => 'm',
+        VirtualKeyCode::N => 'n',
+        VirtualKeyCode::O => 'o',
+        VirtualKeyCode::P => 'p',
+        VirtualKeyCode::Q => 'q',
+        VirtualKeyCode::R => 'r',
+        VirtualKeyCode::S => 's',
+        VirtualKeyCode::T => 't',
+        VirtualKeyCode::U => 'u',
+        VirtualKeyCode::V => 'v',
+        VirtualKeyCode::W => 'w',
+        VirtualKeyCode::X => 'x',
+        VirtualKeyCode::Y => 'y',
+        VirtualKeyCode::Z => 'z',
+        _ => return None,
+    };
+
+    Some(if shift { c.to_ascii_uppercase() } else { c })
+}
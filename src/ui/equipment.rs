@@ -1,15 +1,17 @@
 use super::{
     common::draw_list, common::draw_named_box, WINDOW_HEIGHT, WINDOW_WIDTH, X_OFFSET, Y_OFFSET,
 };
-use crate::components::{Equipable, Equipment, Inventory, Name, SelectedItem};
+use crate::components::{
+    Equipable, Equipment, Inventory, MeleeWeapon, MissileWeapon, Name, SelectedItem,
+};
 use bracket_lib::prelude::*;
 use specs::prelude::*;
 
 /*
  *
- * inventory.rs
+ * equipment.rs
  * ------------
- * UI regarding the inventory screen.
+ * UI regarding the equipment screen.
  *
  */
 
@@ -36,6 +38,8 @@ pub fn show_equipment(
     let equipments = ecs.read_storage::<Equipment>();
     let backpack = ecs.read_storage::<Inventory>();
     let equipable = ecs.read_storage::<Equipable>();
+    let melee = ecs.read_storage::<MeleeWeapon>();
+    let missile = ecs.read_storage::<MissileWeapon>();
     let entities = ecs.entities();
 
     let mut equips_vec: Vec<(String, Entity)> = Vec::new();
@@ -62,7 +66,20 @@ pub fn show_equipment(
     let h = Y - Y_OFFSET - 25;
 
     draw_named_box("·EQUIPMENT·", x1, y1, w, h, draw_batch);
-    let equips_names_vec: Vec<String> = equips_vec.clone().into_iter().map(|x| x.0).collect();
+    // Weapons show their damage range and average next to their names.
+    let equips_names_vec: Vec<String> = equips_vec
+        .iter()
+        .map(|(name, ent)| {
+            let damage = melee
+                .get(*ent)
+                .map(|m| &m.stats.damage)
+                .or_else(|| missile.get(*ent).map(|m| &m.stats.damage));
+            match damage {
+                Some(dice) => format!("{} - {}", name, dice.describe()),
+                None => name.to_string(),
+            }
+        })
+        .collect();
     draw_list(equips_names_vec, x1, y1, draw_batch);
 
     let equips_len = equips_vec.len() as i32;
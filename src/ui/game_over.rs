@@ -0,0 +1,42 @@
+use super::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::morgue::{summary, MORGUE_DIR};
+use crate::utils::colors::*;
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+/*
+ *
+ * game_over.rs
+ * ------------
+ * The death screen, with the summary of the run.
+ *
+ */
+
+pub fn show_game_over(ecs: &World, draw_batch: &mut DrawBatch) {
+    let bg = color("Background", 1.0);
+    let white = ColorPair::new(color("BrightWhite", 1.0), bg);
+    let gray = ColorPair::new(color("BrightBlack", 1.0), bg);
+
+    draw_batch.draw_hollow_box(
+        Rect::with_size(0, 0, WINDOW_WIDTH - 1, WINDOW_HEIGHT - 1),
+        gray,
+    );
+    draw_batch.print_color_centered(10, "YOU DIED", ColorPair::new(color("BrightRed", 1.0), bg));
+
+    let lines = summary(ecs);
+    let x = (WINDOW_WIDTH - lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as i32) / 2;
+    for (i, line) in lines.iter().enumerate() {
+        draw_batch.print_color(Point::new(x, 14 + i as i32), line, white);
+    }
+
+    draw_batch.print_color_centered(
+        WINDOW_HEIGHT - 8,
+        format!("The tale of your journey rests in {}.", MORGUE_DIR),
+        gray,
+    );
+    draw_batch.print_color_centered(
+        WINDOW_HEIGHT - 6,
+        "Press Enter to return to the main menu.",
+        gray,
+    );
+}
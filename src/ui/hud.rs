@@ -1,7 +1,9 @@
 use super::{Log, WINDOW_HEIGHT, WINDOW_WIDTH, X_OFFSET, Y_OFFSET};
 use crate::components::{
-    ActiveWeapon, BaseStats, EquipSlot, EquipSlot::*, Equipable, Equipment, MissileWeapon, Name,
+    ActiveWeapon, BaseStats, EquipSlot, EquipSlot::*, Equipable, Equipment, Experience,
+    MissileWeapon, Name, Skills, StatusEffects, StatusType, Survival, SURVIVAL_LOW, SURVIVAL_MAX,
 };
+use crate::state::RunSeed;
 use crate::utils::colors::*;
 use bracket_lib::prelude::*;
 use specs::prelude::*;
@@ -97,6 +99,39 @@ pub fn name_stats(ecs: &World, draw_batch: &mut DrawBatch) {
         ColorPair::new(white, black),
     );
 
+    // Level and progress towards the next one.
+    let experience = ecs.read_storage::<Experience>();
+    if let Some(exp) = experience.get(*player) {
+        let yellow = color("BrightYellow", 1.0);
+        let prev_level_xp = Experience {
+            level: exp.level - 1,
+            xp: 0,
+        }
+        .next_level_xp();
+        draw_batch.print_color(
+            Point::new(bar_end + 4, y),
+            format!("Lv {}", exp.level),
+            ColorPair::new(yellow, black),
+        );
+        draw_batch.set(
+            Point::new(1, y + 1),
+            ColorPair::new(yellow, black),
+            to_cp437('☼'),
+        );
+        draw_batch.bar_horizontal(
+            Point::new(3, y + 1),
+            bar_end,
+            exp.xp - prev_level_xp,
+            exp.next_level_xp() - prev_level_xp,
+            ColorPair::new(color("Yellow", 1.0), black),
+        );
+        draw_batch.print_color(
+            Point::new(bar_end + 4, y + 1),
+            format!("{}/{}", exp.xp, exp.next_level_xp()),
+            ColorPair::new(white, black),
+        );
+    }
+
     let mut health_status = "• Fine";
     let mut health_status_color = RGB::from_hex(GRASS_GREEN).unwrap();
     if player_stats.health.hp < player_stats.health.max_hp / 10 + 2 {
@@ -112,6 +147,25 @@ pub fn name_stats(ecs: &World, draw_batch: &mut DrawBatch) {
         health_status,
         ColorPair::new(health_status_color, black),
     );
+
+    // Active status effects, two per line.
+    let statuses = ecs.read_storage::<StatusEffects>();
+    if let Some(statuses) = statuses.get(*player) {
+        for (i, effect) in statuses.effects.iter().enumerate() {
+            let status_color = match effect.status {
+                StatusType::Bleeding => red,
+                StatusType::Poisoned => color("BrightGreen", 1.0),
+                StatusType::Stunned => color("BrightYellow", 1.0),
+                StatusType::Burning => color("Yellow", 1.0),
+            };
+            let status = format!("{}", effect.status);
+            draw_batch.print_color(
+                Point::new(2 + 9 * (i as i32 % 2), y + 5 + i as i32 / 2),
+                format!("{}{}", status[..1].to_uppercase(), &status[1..]),
+                ColorPair::new(status_color, black),
+            );
+        }
+    }
 }
 
 pub fn show_equipped(ecs: &World, draw_batch: &mut DrawBatch) {
@@ -255,6 +309,120 @@ pub fn show_equipped(ecs: &World, draw_batch: &mut DrawBatch) {
     );
 }
 
+/// Renders the player's attributes and the weapon skills they've trained.
+pub fn show_attributes(ecs: &World, draw_batch: &mut DrawBatch) {
+    let player = ecs.fetch::<Entity>();
+    let stats = ecs.read_storage::<BaseStats>();
+    let skills = ecs.read_storage::<Skills>();
+
+    let black = color("Background", 1.0);
+    let white = color("BrightWhite", 1.0);
+    let gray = color("BrightBlack", 1.0);
+
+    let y = 38;
+    if let Some(stats) = stats.get(*player) {
+        let attrs = &stats.attributes;
+        draw_batch.print_color(
+            Point::new(0, y),
+            "╞═ ATTRIBUTES",
+            ColorPair::new(gray, black),
+        );
+        draw_batch.print_color(
+            Point::new(3, y + 1),
+            format!(
+                "STR {} AGI {} PER {}",
+                attrs.strength, attrs.agility, attrs.perception
+            ),
+            ColorPair::new(white, black),
+        );
+    }
+
+    if let Some(skills) = skills.get(*player) {
+        let mut trained: Vec<(String, i32)> = skills
+            .melee
+            .iter()
+            .map(|(class, skill)| (class.to_string(), skill.level))
+            .chain(
+                skills
+                    .missile
+                    .iter()
+                    .map(|(class, skill)| (class.to_string(), skill.level)),
+            )
+            .filter(|(_, level)| *level > 0)
+            .collect();
+        trained.sort();
+        draw_batch.print_color(
+            Point::new(0, y + 3),
+            "╞═ SKILLS",
+            ColorPair::new(gray, black),
+        );
+        for (i, (class, level)) in trained.iter().enumerate() {
+            draw_batch.print_color(
+                Point::new(3, y + 4 + i as i32),
+                format!("{:<10}{:>3}", class, level),
+                ColorPair::new(white, black),
+            );
+        }
+    }
+}
+
+/// Renders the player's hunger, thirst and fatigue meters, red once they run low.
+pub fn show_survival(ecs: &World, draw_batch: &mut DrawBatch) {
+    let player = ecs.fetch::<Entity>();
+    let survival = ecs.read_storage::<Survival>();
+
+    let black = color("Background", 1.0);
+    let white = color("BrightWhite", 1.0);
+    let gray = color("BrightBlack", 1.0);
+
+    let y = 50;
+    if let Some(meters) = survival.get(*player) {
+        let title = if meters.resting {
+            "╞═ SURVIVAL (resting)"
+        } else {
+            "╞═ SURVIVAL"
+        };
+        draw_batch.print_color(Point::new(0, y), title, ColorPair::new(gray, black));
+        let bars = [
+            ("Food", meters.hunger, "Yellow"),
+            ("Water", meters.thirst, "Cyan"),
+            ("Rest", meters.fatigue, "Green"),
+        ];
+        for (i, &(label, value, fg)) in bars.iter().enumerate() {
+            let y = y + 1 + i as i32;
+            let fg = if value < SURVIVAL_LOW {
+                "BrightRed"
+            } else {
+                fg
+            };
+            draw_batch.print_color(Point::new(3, y), label, ColorPair::new(white, black));
+            draw_batch.bar_horizontal(
+                Point::new(9, y),
+                X_OFFSET - 11,
+                value,
+                SURVIVAL_MAX,
+                ColorPair::new(color(fg, 1.0), black),
+            );
+        }
+    }
+}
+
+/// Renders the seed of the current run, so it can be reported and reproduced.
+pub fn show_seed(ecs: &World, draw_batch: &mut DrawBatch) {
+    let seed = ecs.fetch::<RunSeed>();
+
+    let black = color("Background", 1.0);
+    let gray = color("BrightBlack", 1.0);
+
+    let y = Y - 4;
+    draw_batch.print_color(Point::new(0, y), "╞═ SEED", ColorPair::new(gray, black));
+    draw_batch.print_color(
+        Point::new(3, y + 1),
+        format!("{:x}", seed.0),
+        ColorPair::new(gray, black),
+    );
+}
+
 /// Renders messages from the log structure.
 pub fn game_log(ecs: &World, draw_batch: &mut DrawBatch) {
     let log = ecs.fetch::<Log>();
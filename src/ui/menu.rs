@@ -1,6 +1,7 @@
 use super::{WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::utils::colors::*;
 use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
 //use specs::prelude::*;
 
 /*
@@ -12,7 +13,7 @@ use bracket_lib::prelude::*;
  *
  */
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum MenuSelection {
     NewGame,
     LoadGame,
@@ -1,8 +1,10 @@
 use super::{log::Log, RunState, WINDOW_HEIGHT, WINDOW_WIDTH, X_OFFSET, Y_OFFSET};
 
+pub mod character;
 mod common;
 pub mod container;
 pub mod equipment;
+pub mod game_over;
 pub mod hud;
 pub mod inventory;
 pub mod menu;
@@ -1,5 +1,8 @@
 use super::common::Popup;
+use crate::components::{Experience, LevelUpChoice};
+use crate::player::LEVEL_UP_HP;
 use bracket_lib::prelude::*;
+use specs::prelude::*;
 
 /*
  *
@@ -15,3 +18,29 @@ pub fn show_context_dir(draw_batch: &mut DrawBatch) {
     popup.add(format!("Press a movement key\nto indicate direction."));
     popup.render_popup(draw_batch);
 }
+
+/// Asks which stat to raise on level up. Returns it once the player chooses.
+pub fn show_level_up(
+    ecs: &World,
+    term: &mut BTerm,
+    draw_batch: &mut DrawBatch,
+) -> Option<LevelUpChoice> {
+    let player = ecs.fetch::<Entity>();
+    let experience = ecs.read_storage::<Experience>();
+    let level = experience.get(*player).map_or(1, |exp| exp.level);
+
+    let mut popup = Popup::new();
+    popup.add(format!("You reached level {}!", level + 1));
+    popup.add("Choose what to improve:".to_string());
+    popup.add(format!("\n(a) Health: +{} max HP", LEVEL_UP_HP));
+    popup.add("(b) Defense: +1".to_string());
+    popup.add("(c) Attack: +1 damage".to_string());
+    popup.render_popup(draw_batch);
+
+    match term.key {
+        Some(VirtualKeyCode::A) => Some(LevelUpChoice::Health),
+        Some(VirtualKeyCode::B) => Some(LevelUpChoice::Defense),
+        Some(VirtualKeyCode::C) => Some(LevelUpChoice::Attack),
+        _ => None,
+    }
+}
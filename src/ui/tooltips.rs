@@ -1,6 +1,6 @@
 use super::{common::Popup, WINDOW_HEIGHT, WINDOW_WIDTH, X_OFFSET, Y_OFFSET};
 use crate::components::{
-    Armor, BaseStats, Description, Item, MeleeWeapon, MissileWeapon, Name, Position,
+    Armor, BaseStats, Description, Explosive, Item, MeleeWeapon, MissileWeapon, Name, Position,
 };
 use crate::map_gen::Map;
 use bracket_lib::prelude::*;
@@ -35,6 +35,7 @@ pub fn show_tooltip(
     let melee = ecs.read_storage::<MeleeWeapon>();
     let missile = ecs.read_storage::<MissileWeapon>();
     let armor = ecs.read_storage::<Armor>();
+    let explosives = ecs.read_storage::<Explosive>();
     let item = ecs.read_storage::<Item>();
     let entities = ecs.entities();
 
@@ -49,13 +50,37 @@ pub fn show_tooltip(
                 ttip.add(format!("\nHP: {}", s.health.hp));
             }
             if let Some(m) = melee.get(ent) {
-                ttip.add(format!("\n{:?}\n\nDMG: {}", m.class, m.stats.base_damage));
+                ttip.add(format!(
+                    "\n{:?}\n\nDMG: {} {}",
+                    m.class,
+                    m.stats.damage.describe(),
+                    m.stats.damage_type
+                ));
+                if let Some(on_hit) = &m.stats.on_hit {
+                    ttip.add(format!("{}: {}%", on_hit.status, on_hit.chance));
+                }
             }
             if let Some(m) = missile.get(ent) {
-                ttip.add(format!("\n{:?}\n\nDMG: {}", m.class, m.stats.base_damage));
+                ttip.add(format!(
+                    "\n{:?}\n\nDMG: {} {}",
+                    m.class,
+                    m.stats.damage.describe(),
+                    m.stats.damage_type
+                ));
+                if let Some(e) = explosives.get(ent) {
+                    ttip.add(format!("Blast radius: {}", e.radius));
+                }
+                if let Some(on_hit) = &m.stats.on_hit {
+                    ttip.add(format!("{}: {}%", on_hit.status, on_hit.chance));
+                }
             }
             if let Some(a) = armor.get(ent) {
                 ttip.add(format!("\nDEF: {}", a.defense));
+                let mut res: Vec<_> = a.resistances.iter().collect();
+                res.sort_by_key(|(dtype, _)| dtype.to_string());
+                for (dtype, amount) in res {
+                    ttip.add(format!("{}: {:+}", dtype, amount));
+                }
             }
             if let Some(t) = item.get(ent) {
                 ttip.add(format!("\nTier: {}", t.tier));
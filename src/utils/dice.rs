@@ -0,0 +1,502 @@
+use bracket_lib::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/*
+ *
+ * dice.rs
+ * -------
+ * Dice expressions, as used by the damage strings in the raws. An expression is a sum of terms,
+ * each one either a flat number or a roll of dice:
+ * * `2d6+1d4-1`: several terms, added or subtracted;
+ * * `4d6k3`: roll 4d6 and keep the 3 highest;
+ * * `2d6!`: exploding dice, rolling again (and adding) whenever a die shows its highest face.
+ *
+ */
+
+const MAX_EXPLOSIONS: i32 = 3; // Rerolls per exploding die, so every expression has a maximum.
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DiceTerm {
+    Flat(i32),
+    Roll {
+        count: i32,
+        faces: i32,
+        keep: Option<i32>, // Highest dice kept; all of them if None.
+        explode: bool,
+        negative: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dice {
+    pub terms: Vec<DiceTerm>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiceError {
+    pub expr: String,
+    pub reason: String,
+    pub entry: Option<String>, // Raws entry the expression came from.
+}
+
+impl DiceError {
+    fn new(expr: &str, reason: &str) -> Self {
+        DiceError {
+            expr: expr.to_string(),
+            reason: reason.to_string(),
+            entry: None,
+        }
+    }
+
+    /// Names the raws entry the expression came from.
+    pub fn in_entry(mut self, entry: &str) -> Self {
+        self.entry = Some(entry.to_string());
+        self
+    }
+}
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(entry) = &self.entry {
+            write!(f, "raws entry \"{}\": ", entry)?;
+        }
+        write!(
+            f,
+            "invalid dice expression \"{}\": {}",
+            self.expr, self.reason
+        )
+    }
+}
+
+impl std::error::Error for DiceError {}
+
+impl FromStr for Dice {
+    type Err = DiceError;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        // Whitespace may only go around the signs between terms, so "2d6 3" isn't read as 2d63.
+        let chars: Vec<char> = expr.chars().collect();
+        if expr.trim().is_empty() {
+            return Err(DiceError::new(expr, "empty expression"));
+        }
+
+        let mut terms = Vec::new();
+        let mut i = 0;
+        skip_whitespace(&chars, &mut i);
+        while i < chars.len() {
+            let negative = match chars[i] {
+                '-' => true,
+                '+' => false,
+                _ if terms.is_empty() => false,
+                c => return Err(DiceError::new(expr, &format!("unexpected '{}'", c))),
+            };
+            if chars[i] == '-' || chars[i] == '+' {
+                i += 1;
+                skip_whitespace(&chars, &mut i);
+            }
+
+            let count = read_number(&chars, &mut i);
+            if i < chars.len() && chars[i] == 'd' {
+                i += 1;
+                let count = count.unwrap_or(1);
+                let faces = match read_number(&chars, &mut i) {
+                    Some(faces) => faces,
+                    None => return Err(DiceError::new(expr, "missing number of faces")),
+                };
+                let mut keep = None;
+                let mut explode = false;
+                while i < chars.len() && (chars[i] == 'k' || chars[i] == '!') {
+                    if chars[i] == '!' {
+                        if explode {
+                            return Err(DiceError::new(expr, "repeated '!'"));
+                        }
+                        explode = true;
+                        i += 1;
+                    } else {
+                        if keep.is_some() {
+                            return Err(DiceError::new(expr, "repeated 'k'"));
+                        }
+                        i += 1;
+                        if i < chars.len() && chars[i] == 'h' {
+                            i += 1;
+                        }
+                        match read_number(&chars, &mut i) {
+                            Some(k) => keep = Some(k),
+                            None => {
+                                return Err(DiceError::new(expr, "missing number of kept dice"))
+                            }
+                        }
+                    }
+                }
+
+                if count < 1 || faces < 1 {
+                    return Err(DiceError::new(
+                        expr,
+                        "dice must have at least 1 die and 1 face",
+                    ));
+                }
+                if matches!(keep, Some(k) if k < 1 || k > count) {
+                    return Err(DiceError::new(expr, "can only keep from 1 to all the dice"));
+                }
+                if explode && faces < 2 {
+                    return Err(DiceError::new(expr, "exploding dice need at least 2 faces"));
+                }
+                terms.push(DiceTerm::Roll {
+                    count,
+                    faces,
+                    keep,
+                    explode,
+                    negative,
+                });
+            } else {
+                match count {
+                    Some(n) => terms.push(DiceTerm::Flat(if negative { -n } else { n })),
+                    None if i < chars.len() => {
+                        return Err(DiceError::new(expr, &format!("unexpected '{}'", chars[i])))
+                    }
+                    None => return Err(DiceError::new(expr, "expression ends with a sign")),
+                }
+            }
+            skip_whitespace(&chars, &mut i);
+        }
+
+        Ok(Dice { terms })
+    }
+}
+
+fn skip_whitespace(chars: &[char], i: &mut usize) {
+    while *i < chars.len() && chars[*i].is_whitespace() {
+        *i += 1;
+    }
+}
+
+/// Reads the (non-negative) number starting at `chars[*i]`, if there's one, moving `i` past it.
+fn read_number(chars: &[char], i: &mut usize) -> Option<i32> {
+    let start = *i;
+    while *i < chars.len() && chars[*i].is_ascii_digit() {
+        *i += 1;
+    }
+    chars[start..*i].iter().collect::<String>().parse().ok()
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            match term {
+                DiceTerm::Flat(n) => {
+                    if *n < 0 {
+                        write!(f, "-{}", -n)?;
+                    } else if i > 0 {
+                        write!(f, "+{}", n)?;
+                    } else {
+                        write!(f, "{}", n)?;
+                    }
+                }
+                DiceTerm::Roll {
+                    count,
+                    faces,
+                    keep,
+                    explode,
+                    negative,
+                } => {
+                    if *negative {
+                        write!(f, "-")?;
+                    } else if i > 0 {
+                        write!(f, "+")?;
+                    }
+                    write!(f, "{}d{}", count, faces)?;
+                    if let Some(k) = keep {
+                        write!(f, "k{}", k)?;
+                    }
+                    if *explode {
+                        write!(f, "!")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Dice {
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        let mut total = 0;
+        for term in self.terms.iter() {
+            match term {
+                DiceTerm::Flat(n) => total += n,
+                DiceTerm::Roll {
+                    count,
+                    faces,
+                    keep,
+                    explode,
+                    negative,
+                } => {
+                    let mut rolls: Vec<i32> = (0..*count)
+                        .map(|_| {
+                            let mut roll = rng.roll_dice(1, *faces);
+                            let mut last = roll;
+                            let mut explosions = 0;
+                            while *explode && last == *faces && explosions < MAX_EXPLOSIONS {
+                                last = rng.roll_dice(1, *faces);
+                                roll += last;
+                                explosions += 1;
+                            }
+                            roll
+                        })
+                        .collect();
+                    rolls.sort_unstable_by(|a, b| b.cmp(a));
+                    let sum: i32 = rolls.iter().take(keep.unwrap_or(*count) as usize).sum();
+                    total += if *negative { -sum } else { sum };
+                }
+            }
+        }
+
+        total
+    }
+
+    pub fn min(&self) -> i32 {
+        self.terms
+            .iter()
+            .map(|term| match term {
+                DiceTerm::Flat(n) => *n,
+                DiceTerm::Roll { negative: true, .. } => -term_max(term),
+                DiceTerm::Roll { .. } => term_min(term),
+            })
+            .sum()
+    }
+
+    pub fn max(&self) -> i32 {
+        self.terms
+            .iter()
+            .map(|term| match term {
+                DiceTerm::Flat(n) => *n,
+                DiceTerm::Roll { negative: true, .. } => -term_min(term),
+                DiceTerm::Roll { .. } => term_max(term),
+            })
+            .sum()
+    }
+
+    pub fn average(&self) -> f32 {
+        self.terms
+            .iter()
+            .map(|term| match term {
+                DiceTerm::Flat(n) => *n as f64,
+                DiceTerm::Roll {
+                    count,
+                    faces,
+                    keep,
+                    explode,
+                    negative,
+                } => {
+                    let avg = kept_average(*count, keep.unwrap_or(*count), *faces, *explode);
+                    if *negative {
+                        -avg
+                    } else {
+                        avg
+                    }
+                }
+            })
+            .sum::<f64>() as f32
+    }
+
+    /// E.g. "1d8+5 (6-13, avg 9.5)".
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({}-{}, avg {:.1})",
+            self,
+            self.min(),
+            self.max(),
+            self.average()
+        )
+    }
+}
+
+fn kept(term: &DiceTerm) -> i32 {
+    match term {
+        DiceTerm::Roll { count, keep, .. } => keep.unwrap_or(*count),
+        DiceTerm::Flat(_) => 0,
+    }
+}
+
+fn term_min(term: &DiceTerm) -> i32 {
+    kept(term)
+}
+
+fn term_max(term: &DiceTerm) -> i32 {
+    match term {
+        DiceTerm::Roll { faces, explode, .. } => {
+            let die_max = if *explode {
+                faces * (MAX_EXPLOSIONS + 1)
+            } else {
+                *faces
+            };
+            kept(term) * die_max
+        }
+        DiceTerm::Flat(n) => *n,
+    }
+}
+
+/// Chance of a single die showing each total, indexed by the total.
+fn die_distribution(faces: i32, explode: bool) -> Vec<f64> {
+    let p = 1.0 / faces as f64;
+    let explosions = if explode { MAX_EXPLOSIONS } else { 0 };
+    let mut dist = vec![0.0; (faces * (explosions + 1) + 1) as usize];
+    for k in 0..=explosions {
+        // The highest face only stops the die on its last roll.
+        let last_face = if k == explosions { faces } else { faces - 1 };
+        for v in 1..=last_face {
+            dist[(k * faces + v) as usize] += p.powi(k + 1);
+        }
+    }
+
+    dist
+}
+
+/// Average of the sum of the `keep` highest of `count` dice. The i-th highest die is at least x
+/// whenever at least i dice are, so its average is the sum of those chances over every x.
+fn kept_average(count: i32, keep: i32, faces: i32, explode: bool) -> f64 {
+    let dist = die_distribution(faces, explode);
+    let mut avg = 0.0;
+    for x in 1..dist.len() {
+        let tail: f64 = dist[x..].iter().sum();
+        for i in 1..=keep {
+            avg += (i..=count)
+                .map(|j| binomial(count, j) * tail.powi(j) * (1.0 - tail).powi(count - j))
+                .sum::<f64>();
+        }
+    }
+
+    avg
+}
+
+fn binomial(n: i32, k: i32) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(expr: &str) -> Dice {
+        expr.parse()
+            .unwrap_or_else(|e| panic!("\"{}\" should parse: {}", expr, e))
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for expr in ["2d6+1d4-1", "4d6k3", "2d6!", "1d6", "-1d4+3", "3d8k2!"].iter() {
+            let dice = parse(expr);
+            assert_eq!(dice.to_string(), *expr);
+            assert_eq!(parse(&dice.to_string()), dice);
+        }
+        assert_eq!(parse("d6").to_string(), "1d6");
+        assert_eq!(parse("4d6kh3"), parse("4d6k3"));
+        assert_eq!(parse(" 2d6 + 1d4 - 1 "), parse("2d6+1d4-1"));
+    }
+
+    #[test]
+    fn parses_terms() {
+        assert_eq!(
+            parse("2d6+1d4-1").terms,
+            vec![
+                DiceTerm::Roll {
+                    count: 2,
+                    faces: 6,
+                    keep: None,
+                    explode: false,
+                    negative: false,
+                },
+                DiceTerm::Roll {
+                    count: 1,
+                    faces: 4,
+                    keep: None,
+                    explode: false,
+                    negative: false,
+                },
+                DiceTerm::Flat(-1),
+            ]
+        );
+        assert_eq!(
+            parse("4d6k3").terms,
+            vec![DiceTerm::Roll {
+                count: 4,
+                faces: 6,
+                keep: Some(3),
+                explode: false,
+                negative: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for expr in [
+            "", "  ", "1d", "2d6k0", "2d6k3k1", "2d6k", "1d1!", "2d6!!", "0d6", "1d0", "1d6+",
+            "1d6-", "+", "2d6 3", "2 d6", "1d 6", "2d6x", "1d6++1",
+        ]
+        .iter()
+        {
+            assert!(
+                expr.parse::<Dice>().is_err(),
+                "\"{}\" should be rejected",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn errors_name_the_raws_entry() {
+        let err = "2d6 3".parse::<Dice>().unwrap_err().in_entry("Tantou");
+        assert_eq!(err.entry.as_deref(), Some("Tantou"));
+        assert!(err.to_string().starts_with("raws entry \"Tantou\": "));
+    }
+
+    #[test]
+    fn min_max_average() {
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+        let dice = parse("2d6+1d4-1");
+        assert_eq!((dice.min(), dice.max()), (2, 15));
+        assert!(close(dice.average(), 8.5));
+
+        let dice = parse("4d6k3");
+        assert_eq!((dice.min(), dice.max()), (3, 18));
+        assert!(close(dice.average(), 12.24));
+
+        // Each die explodes at most MAX_EXPLOSIONS times.
+        let dice = parse("2d6!");
+        assert_eq!((dice.min(), dice.max()), (2, 48));
+        assert!(close(
+            dice.average(),
+            2.0 * 3.5 * (1.0 + 1.0 / 6.0 + 1.0 / 36.0 + 1.0 / 216.0)
+        ));
+
+        let dice = parse("d6");
+        assert_eq!((dice.min(), dice.max()), (1, 6));
+        assert!(close(dice.average(), 3.5));
+
+        let dice = parse("5-1d4");
+        assert_eq!((dice.min(), dice.max()), (1, 4));
+        assert!(close(dice.average(), 2.5));
+    }
+
+    #[test]
+    fn rolls_stay_in_range() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        for expr in ["2d6+1d4-1", "4d6k3", "2d6!", "5-1d4"].iter() {
+            let dice = parse(expr);
+            for _ in 0..1000 {
+                let roll = dice.roll(&mut rng);
+                assert!(
+                    roll >= dice.min() && roll <= dice.max(),
+                    "{} rolled {}",
+                    expr,
+                    roll
+                );
+            }
+        }
+    }
+}
@@ -95,8 +95,7 @@ pub fn dir_idx(idx: usize) -> Direction {
 }
 
 #[allow(dead_code)]
-pub fn get_random_dir() -> Direction {
-    let mut rng = RandomNumberGenerator::new();
+pub fn get_random_dir(rng: &mut RandomNumberGenerator) -> Direction {
     let dir = rng.range(0, 4);
 
     match dir {
@@ -1,4 +1,5 @@
 #![allow(dead_code)]
 
 pub mod colors;
+pub mod dice;
 pub mod directions;